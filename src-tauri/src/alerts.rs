@@ -0,0 +1,162 @@
+use regex::Regex;
+use crate::models::*;
+use crate::numbers::parse_number;
+use log::warn;
+
+/// Returns every rule of the job that fires for this result.
+pub fn evaluate_alerts(job: &ScrapingJob, result: &ScrapingResult) -> Vec<AlertTrigger> {
+    job.alert_rules
+        .iter()
+        .filter_map(|rule| evaluate_rule(rule, job.number_locale(), result))
+        .collect()
+}
+
+fn evaluate_rule(rule: &AlertRule, locale: &str, result: &ScrapingResult) -> Option<AlertTrigger> {
+    let trigger = |reason: String, values: Vec<String>| {
+        Some(AlertTrigger { rule: rule.clone(), reason, values })
+    };
+
+    match rule {
+        AlertRule::OnFailure => {
+            if result.success {
+                return None;
+            }
+            let error = result.error_message.clone().unwrap_or_default();
+            trigger("The run failed".to_string(), vec![error])
+        }
+        // The remaining rules only look at successful runs
+        _ if !result.success => None,
+        AlertRule::OnChange => {
+            if result.changed != Some(true) {
+                return None;
+            }
+            let values = result.diff.as_ref()
+                .map(|diff| diff.added.iter().chain(&diff.removed).cloned().collect())
+                .unwrap_or_default();
+            trigger("The content changed since the last run".to_string(), values)
+        }
+        AlertRule::NoItems => {
+            if !result.item_values().is_empty() {
+                return None;
+            }
+            trigger("The run extracted no items".to_string(), Vec::new())
+        }
+        AlertRule::ItemMatches { pattern, field } => {
+            let regex = match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(e) => {
+                    warn!("Skipping alert with invalid regex '{}': {}", pattern, e);
+                    return None;
+                }
+            };
+            let matches: Vec<String> = rule_values(result, field.as_deref())
+                .into_iter()
+                .filter(|value| regex.is_match(value))
+                .collect();
+            if matches.is_empty() {
+                return None;
+            }
+            trigger(format!("{} item(s) matched /{}/", matches.len(), pattern), matches)
+        }
+        AlertRule::ValueBelow { threshold, field } => {
+            let below: Vec<String> = rule_values(result, field.as_deref())
+                .into_iter()
+                .filter(|value| parse_number(value, locale).is_some_and(|number| number < *threshold))
+                .collect();
+            if below.is_empty() {
+                return None;
+            }
+            trigger(format!("{} value(s) dropped below {}", below.len(), threshold), below)
+        }
+    }
+}
+
+// Values a rule applies to: a single field of each record, or every item.
+fn rule_values(result: &ScrapingResult, field: Option<&str>) -> Vec<String> {
+    match field {
+        Some(field) => result.records
+            .iter()
+            .filter_map(|record| record.get(field).cloned())
+            .collect(),
+        None => result.item_values(),
+    }
+}
+
+/// Builds the subject and body of the notification for a run.
+pub fn format_alert_email(job: &ScrapingJob, result: &ScrapingResult, triggers: &[AlertTrigger]) -> (String, String) {
+    let subject = format!("Web Scraping Alert: {} ({} rule(s) triggered)", job.name, triggers.len());
+
+    let mut body = format!(
+        "Hello,\n\nThe web scraping job '{}' triggered the following alerts.\n\nJob Details:\n- Job Name: {}\n- URL: {}\n- Run: {}\n",
+        job.name,
+        job.name,
+        job.url,
+        result.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+
+    for trigger in triggers {
+        body.push_str(&format!("\n{}\n", trigger.reason));
+        for value in trigger.values.iter().take(20) {
+            body.push_str(&format!("  - {}\n", value));
+        }
+        if trigger.values.len() > 20 {
+            body.push_str(&format!("  ... and {} more\n", trigger.values.len() - 20));
+        }
+    }
+
+    body.push_str("\nBest regards,\nAutomated Web Scraper");
+    (subject, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::numbers::DEFAULT_LOCALE;
+
+    fn result_with_items(items: &[&str]) -> ScrapingResult {
+        ScrapingResult {
+            id: None,
+            job_id: 1,
+            scraped_data: items.join("\n"),
+            items: items.iter().map(|item| item.to_string()).collect(),
+            records: Vec::new(),
+            timestamp: Utc::now(),
+            success: true,
+            error_message: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rule_evaluation() {
+        let result = result_with_items(&["$25.00", "$9.99", "Out of stock"]);
+
+        let below = evaluate_rule(&AlertRule::ValueBelow { threshold: 10.0, field: None }, DEFAULT_LOCALE, &result).unwrap();
+        assert_eq!(below.values, vec!["$9.99".to_string()]);
+
+        // "9,99 €" is below ten only when read as German
+        let german = result_with_items(&["9,99 €", "12,50 €"]);
+        let rule = AlertRule::ValueBelow { threshold: 10.0, field: None };
+        assert!(evaluate_rule(&rule, DEFAULT_LOCALE, &german).is_none());
+        assert_eq!(evaluate_rule(&rule, "de-DE", &german).unwrap().values, vec!["9,99 €".to_string()]);
+
+        let matches = evaluate_rule(
+            &AlertRule::ItemMatches { pattern: "(?i)out of stock".to_string(), field: None },
+            DEFAULT_LOCALE,
+            &result,
+        ).unwrap();
+        assert_eq!(matches.values.len(), 1);
+
+        assert!(evaluate_rule(&AlertRule::NoItems, DEFAULT_LOCALE, &result).is_none());
+        assert!(evaluate_rule(&AlertRule::NoItems, DEFAULT_LOCALE, &result_with_items(&[])).is_some());
+        assert!(evaluate_rule(&AlertRule::OnChange, DEFAULT_LOCALE, &result).is_none());
+        assert!(evaluate_rule(&AlertRule::OnFailure, DEFAULT_LOCALE, &result).is_none());
+
+        let mut failed = result_with_items(&[]);
+        failed.success = false;
+        failed.error_message = Some("HTTP error: 500".to_string());
+        assert!(evaluate_rule(&AlertRule::OnFailure, DEFAULT_LOCALE, &failed).is_some());
+        assert!(evaluate_rule(&AlertRule::NoItems, DEFAULT_LOCALE, &failed).is_none());
+    }
+}
@@ -0,0 +1,90 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use crate::models::*;
+
+/// Hashes the extracted items in order, so reordering counts as a change.
+pub fn content_hash(values: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for value in values {
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Compares two runs item by item. Duplicates are matched one to one, so a
+/// value that appears twice now but once before shows up once as added.
+pub fn diff_values(previous: &[String], current: &[String]) -> ResultDiff {
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for value in previous {
+        *remaining.entry(value.as_str()).or_insert(0) += 1;
+    }
+
+    let mut diff = ResultDiff::default();
+    for value in current {
+        match remaining.get_mut(value.as_str()) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                diff.unchanged.push(value.clone());
+            }
+            _ => diff.added.push(value.clone()),
+        }
+    }
+
+    for value in previous {
+        if let Some(count) = remaining.get_mut(value.as_str()) {
+            if *count > 0 {
+                *count -= 1;
+                diff.removed.push(value.clone());
+            }
+        }
+    }
+
+    diff
+}
+
+/// Fills in the hash, changed flag and diff of a successful result.
+pub fn annotate_changes(result: &mut ScrapingResult, previous: Option<&ScrapingResult>) {
+    if !result.success {
+        return;
+    }
+
+    let values = result.item_values();
+    let hash = content_hash(&values);
+
+    if let Some(previous) = previous {
+        let previous_values = previous.item_values();
+        let previous_hash = previous.content_hash.clone()
+            .unwrap_or_else(|| content_hash(&previous_values));
+
+        result.changed = Some(previous_hash != hash);
+        result.diff = Some(diff_values(&previous_values, &values));
+    }
+
+    result.content_hash = Some(hash);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(&values(&["a", "b"])), content_hash(&values(&["a", "b"])));
+        assert_ne!(content_hash(&values(&["a", "b"])), content_hash(&values(&["b", "a"])));
+        // Item boundaries are part of the hash
+        assert_ne!(content_hash(&values(&["ab"])), content_hash(&values(&["a", "b"])));
+    }
+
+    #[test]
+    fn test_diff_values() {
+        let diff = diff_values(&values(&["a", "b", "b"]), &values(&["b", "c", "c"]));
+        assert_eq!(diff.added, values(&["c", "c"]));
+        assert_eq!(diff.removed, values(&["a", "b"]));
+        assert_eq!(diff.unchanged, values(&["b"]));
+    }
+}
@@ -0,0 +1,67 @@
+use keyring::Entry;
+use anyhow::{Result, anyhow};
+use crate::models::RequestSecrets;
+
+const SERVICE: &str = "automated-web-scraper";
+
+// Login passwords and secret request headers and cookies live in the
+// operating system's credential store rather than in the database, keyed
+// by job
+fn entry(account: &str) -> Result<Entry> {
+    Entry::new(SERVICE, account)
+        .map_err(|e| anyhow!("Failed to open credential store: {}", e))
+}
+
+fn password_entry(job_id: i64) -> Result<Entry> {
+    entry(&format!("job-{}", job_id))
+}
+
+fn request_entry(job_id: i64) -> Result<Entry> {
+    entry(&format!("job-{}-request", job_id))
+}
+
+pub fn set_job_password(job_id: i64, password: &str) -> Result<()> {
+    password_entry(job_id)?
+        .set_password(password)
+        .map_err(|e| anyhow!("Failed to store password: {}", e))
+}
+
+pub fn get_job_password(job_id: i64) -> Result<String> {
+    match password_entry(job_id)?.get_password() {
+        Ok(password) => Ok(password),
+        Err(keyring::Error::NoEntry) => Err(anyhow!("No password is stored for job {}", job_id)),
+        Err(e) => Err(anyhow!("Failed to read password: {}", e)),
+    }
+}
+
+pub fn delete_job_password(job_id: i64) -> Result<()> {
+    delete(password_entry(job_id)?, "password")
+}
+
+pub fn set_job_request_secrets(job_id: i64, secrets: &RequestSecrets) -> Result<()> {
+    if secrets.is_empty() {
+        return delete_job_request_secrets(job_id);
+    }
+    request_entry(job_id)?
+        .set_password(&serde_json::to_string(secrets)?)
+        .map_err(|e| anyhow!("Failed to store request headers and cookies: {}", e))
+}
+
+pub fn get_job_request_secrets(job_id: i64) -> Result<RequestSecrets> {
+    match request_entry(job_id)?.get_password() {
+        Ok(secrets) => Ok(serde_json::from_str(&secrets)?),
+        Err(keyring::Error::NoEntry) => Ok(RequestSecrets::default()),
+        Err(e) => Err(anyhow!("Failed to read request headers and cookies: {}", e)),
+    }
+}
+
+pub fn delete_job_request_secrets(job_id: i64) -> Result<()> {
+    delete(request_entry(job_id)?, "request headers and cookies")
+}
+
+fn delete(entry: Entry, what: &str) -> Result<()> {
+    match entry.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Failed to delete {}: {}", what, e)),
+    }
+}
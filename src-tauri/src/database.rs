@@ -1,77 +1,34 @@
-use rusqlite::{Connection, Result as SqliteResult, params};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult, params};
 use chrono::{DateTime, Utc};
 use std::path::Path;
 use crate::models::*;
+use crate::migrations;
+use crate::session::CookieJar;
+use crate::snapshots;
+use crate::health;
 use anyhow::Result;
 
+const JOB_COLUMNS: &str = "id, name, url, selector_type, selector, data_type, schedule, \
+    user_agent, proxy_url, is_active, created_at, updated_at, item_selector, fields, alert_rules, \
+    retry_policy, accept_invalid_certs, request, login, pagination, \
+    crawl, url_options, transforms, validation, store_snapshots, locale";
+
+const RESULT_COLUMNS: &str = "id, job_id, timestamp, success, error_message, \
+    content_hash, changed, diff, started_at, finished_at, duration_ms, http_status, bytes_fetched, \
+    run_trigger, attempts, failure_reason";
+
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
-        let db = Database { conn };
-        db.init_tables()?;
-        Ok(db)
-    }
-
-    fn init_tables(&self) -> Result<()> {
-        // Create jobs table
-        self.conn.execute(
-            r#"
-            CREATE TABLE IF NOT EXISTS jobs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                url TEXT NOT NULL,
-                selector_type TEXT NOT NULL,
-                selector TEXT NOT NULL,
-                data_type TEXT NOT NULL,
-                schedule TEXT NOT NULL,
-                user_agent TEXT,
-                proxy_url TEXT,
-                is_active BOOLEAN NOT NULL DEFAULT 1,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#,
-            [],
-        )?;
-
-        // Create results table
-        self.conn.execute(
-            r#"
-            CREATE TABLE IF NOT EXISTS results (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                job_id INTEGER NOT NULL,
-                scraped_data TEXT NOT NULL,
-                timestamp TEXT NOT NULL,
-                success BOOLEAN NOT NULL,
-                error_message TEXT,
-                FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE
-            )
-            "#,
-            [],
-        )?;
-
-        // Create email_config table
-        self.conn.execute(
-            r#"
-            CREATE TABLE IF NOT EXISTS email_config (
-                id INTEGER PRIMARY KEY,
-                smtp_server TEXT NOT NULL,
-                smtp_port INTEGER NOT NULL,
-                username TEXT NOT NULL,
-                password TEXT NOT NULL,
-                sender_email TEXT NOT NULL,
-                receiver_email TEXT NOT NULL,
-                use_tls BOOLEAN NOT NULL DEFAULT 1
-            )
-            "#,
-            [],
-        )?;
-
-        Ok(())
+        let conn = Connection::open(&db_path)?;
+        // The bundled SQLite already defaults to this; set it anyway so the
+        // schema's cascades don't depend on how the library was built
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrations::run_pending(&conn, Some(db_path.as_ref()))?;
+        Ok(Database { conn })
     }
 
     pub fn create_job(&self, job: &ScrapingJob) -> Result<i64> {
@@ -79,8 +36,12 @@ impl Database {
         let _id = self.conn.execute(
             r#"
             INSERT INTO jobs (name, url, selector_type, selector, data_type, schedule, 
-                            user_agent, proxy_url, is_active, created_at, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                            user_agent, proxy_url, is_active, created_at, updated_at,
+                            item_selector, fields, alert_rules, retry_policy, accept_invalid_certs,
+                            request, login, pagination, crawl, url_options, transforms,
+                            validation, store_snapshots, locale)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+                    ?20, ?21, ?22, ?23, ?24, ?25)
             "#,
             params![
                 job.name,
@@ -93,7 +54,21 @@ impl Database {
                 job.proxy_url,
                 job.is_active,
                 now,
-                now
+                now,
+                job.item_selector,
+                serde_json::to_string(&job.fields)?,
+                serde_json::to_string(&job.alert_rules)?,
+                serde_json::to_string(&job.retry_policy)?,
+                job.accept_invalid_certs,
+                serde_json::to_string(&job.request)?,
+                job.login.as_ref().map(serde_json::to_string).transpose()?,
+                job.pagination.as_ref().map(serde_json::to_string).transpose()?,
+                job.crawl.as_ref().map(serde_json::to_string).transpose()?,
+                serde_json::to_string(&job.url_options)?,
+                serde_json::to_string(&job.transforms)?,
+                serde_json::to_string(&job.validation)?,
+                job.store_snapshots,
+                job.locale
             ],
         )?;
         Ok(self.conn.last_insert_rowid())
@@ -101,27 +76,10 @@ impl Database {
 
     pub fn get_job(&self, id: i64) -> Result<Option<ScrapingJob>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, url, selector_type, selector, data_type, schedule, 
-                    user_agent, proxy_url, is_active, created_at, updated_at 
-             FROM jobs WHERE id = ?1"
+            &format!("SELECT {} FROM jobs WHERE id = ?1", JOB_COLUMNS)
         )?;
 
-        let job_iter = stmt.query_map([id], |row| {
-            Ok(ScrapingJob {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                url: row.get(2)?,
-                selector_type: row.get::<_, String>(3)?.parse().unwrap(),
-                selector: row.get(4)?,
-                data_type: row.get::<_, String>(5)?.parse().unwrap(),
-                schedule: row.get(6)?,
-                user_agent: row.get(7)?,
-                proxy_url: row.get(8)?,
-                is_active: row.get(9)?,
-                created_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?).unwrap().with_timezone(&Utc)),
-                updated_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?).unwrap().with_timezone(&Utc)),
-            })
-        })?;
+        let job_iter = stmt.query_map([id], row_to_job)?;
 
         for job in job_iter {
             return Ok(Some(job?));
@@ -131,27 +89,10 @@ impl Database {
 
     pub fn get_all_jobs(&self) -> Result<Vec<ScrapingJob>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, url, selector_type, selector, data_type, schedule, 
-                    user_agent, proxy_url, is_active, created_at, updated_at 
-             FROM jobs ORDER BY created_at DESC"
+            &format!("SELECT {} FROM jobs ORDER BY created_at DESC", JOB_COLUMNS)
         )?;
 
-        let job_iter = stmt.query_map([], |row| {
-            Ok(ScrapingJob {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                url: row.get(2)?,
-                selector_type: row.get::<_, String>(3)?.parse().unwrap(),
-                selector: row.get(4)?,
-                data_type: row.get::<_, String>(5)?.parse().unwrap(),
-                schedule: row.get(6)?,
-                user_agent: row.get(7)?,
-                proxy_url: row.get(8)?,
-                is_active: row.get(9)?,
-                created_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?).unwrap().with_timezone(&Utc)),
-                updated_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?).unwrap().with_timezone(&Utc)),
-            })
-        })?;
+        let job_iter = stmt.query_map([], row_to_job)?;
 
         let mut jobs = Vec::new();
         for job in job_iter {
@@ -162,27 +103,10 @@ impl Database {
 
     pub fn get_active_jobs(&self) -> Result<Vec<ScrapingJob>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, url, selector_type, selector, data_type, schedule, 
-                    user_agent, proxy_url, is_active, created_at, updated_at 
-             FROM jobs WHERE is_active = 1"
+            &format!("SELECT {} FROM jobs WHERE is_active = 1", JOB_COLUMNS)
         )?;
 
-        let job_iter = stmt.query_map([], |row| {
-            Ok(ScrapingJob {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                url: row.get(2)?,
-                selector_type: row.get::<_, String>(3)?.parse().unwrap(),
-                selector: row.get(4)?,
-                data_type: row.get::<_, String>(5)?.parse().unwrap(),
-                schedule: row.get(6)?,
-                user_agent: row.get(7)?,
-                proxy_url: row.get(8)?,
-                is_active: row.get(9)?,
-                created_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?).unwrap().with_timezone(&Utc)),
-                updated_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?).unwrap().with_timezone(&Utc)),
-            })
-        })?;
+        let job_iter = stmt.query_map([], row_to_job)?;
 
         let mut jobs = Vec::new();
         for job in job_iter {
@@ -198,8 +122,12 @@ impl Database {
             r#"
             UPDATE jobs SET name = ?1, url = ?2, selector_type = ?3, selector = ?4, 
                           data_type = ?5, schedule = ?6, user_agent = ?7, proxy_url = ?8, 
-                          is_active = ?9, updated_at = ?10
-            WHERE id = ?11
+                          is_active = ?9, updated_at = ?10, item_selector = ?11, fields = ?12,
+                          alert_rules = ?13, retry_policy = ?14, accept_invalid_certs = ?15,
+                          request = ?16, login = ?17, pagination = ?18, crawl = ?19,
+                          url_options = ?20, transforms = ?21, validation = ?22,
+                          store_snapshots = ?23, locale = ?24
+            WHERE id = ?25
             "#,
             params![
                 job.name,
@@ -212,6 +140,20 @@ impl Database {
                 job.proxy_url,
                 job.is_active,
                 now,
+                job.item_selector,
+                serde_json::to_string(&job.fields)?,
+                serde_json::to_string(&job.alert_rules)?,
+                serde_json::to_string(&job.retry_policy)?,
+                job.accept_invalid_certs,
+                serde_json::to_string(&job.request)?,
+                job.login.as_ref().map(serde_json::to_string).transpose()?,
+                job.pagination.as_ref().map(serde_json::to_string).transpose()?,
+                job.crawl.as_ref().map(serde_json::to_string).transpose()?,
+                serde_json::to_string(&job.url_options)?,
+                serde_json::to_string(&job.transforms)?,
+                serde_json::to_string(&job.validation)?,
+                job.store_snapshots,
+                job.locale,
                 job_id
             ],
         )?;
@@ -219,79 +161,239 @@ impl Database {
     }
 
     pub fn delete_job(&self, id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM jobs WHERE id = ?1", [id])?;
+        // The job's results, their items and snapshots, and its cookie jar
+        // go with it through ON DELETE CASCADE
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM jobs WHERE id = ?1", [id])?;
+        // Snapshot bodies are shared between runs, so they are removed only
+        // once nothing refers to them
+        tx.execute(
+            "DELETE FROM snapshot_bodies WHERE hash NOT IN (SELECT body_hash FROM result_snapshots)",
+            [],
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
     pub fn save_result(&self, result: &ScrapingResult) -> Result<i64> {
-        let _id = self.conn.execute(
-            "INSERT INTO results (job_id, scraped_data, timestamp, success, error_message) 
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO results (job_id, timestamp, success, error_message,
+                                  content_hash, changed, diff, started_at, finished_at,
+                                  duration_ms, http_status, bytes_fetched, run_trigger, attempts,
+                                  failure_reason) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
             params![
                 result.job_id,
-                result.scraped_data,
                 result.timestamp.to_rfc3339(),
                 result.success,
-                result.error_message
+                result.error_message,
+                result.content_hash,
+                result.changed,
+                result.diff.as_ref().map(serde_json::to_string).transpose()?,
+                result.started_at.map(|t| t.to_rfc3339()),
+                result.finished_at.map(|t| t.to_rfc3339()),
+                result.duration_ms,
+                result.http_status,
+                result.bytes_fetched,
+                result.trigger.to_string(),
+                serde_json::to_string(&result.attempts)?,
+                result.failure_reason.map(|reason| reason.to_string())
             ],
         )?;
-        Ok(self.conn.last_insert_rowid())
+        let result_id = tx.last_insert_rowid();
+        insert_items(&tx, result_id, &result.items, &result.records)?;
+        insert_snapshots(&tx, result_id, &result.snapshots)?;
+        tx.commit()?;
+        Ok(result_id)
     }
 
-    pub fn get_result(&self, id: i64) -> Result<Option<ScrapingResult>> {
+    /// Replaces the outcome and items of a stored run, keeping its timing
+    /// and attempts. Used when a run is extracted again from its snapshots.
+    pub fn replace_result_data(&self, result: &ScrapingResult) -> Result<()> {
+        let result_id = result.id.ok_or_else(|| anyhow::anyhow!("Result must have an ID"))?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE results SET success = ?1, error_message = ?2, content_hash = ?3,
+                                changed = ?4, diff = ?5, failure_reason = ?6
+             WHERE id = ?7",
+            params![
+                result.success,
+                result.error_message,
+                result.content_hash,
+                result.changed,
+                result.diff.as_ref().map(serde_json::to_string).transpose()?,
+                result.failure_reason.map(|reason| reason.to_string()),
+                result_id
+            ],
+        )?;
+        tx.execute("DELETE FROM result_items WHERE result_id = ?1", [result_id])?;
+        insert_items(&tx, result_id, &result.items, &result.records)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// IDs of the job's runs that have stored pages, oldest first.
+    pub fn get_result_ids_with_snapshots(&self, job_id: i64) -> Result<Vec<i64>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, job_id, scraped_data, timestamp, success, error_message 
-             FROM results WHERE id = ?1"
+            "SELECT id FROM results
+             WHERE job_id = ?1 AND id IN (SELECT result_id FROM result_snapshots)
+             ORDER BY timestamp, id"
         )?;
+        let ids = stmt.query_map([job_id], |row| row.get(0))?
+            .collect::<SqliteResult<Vec<i64>>>()?;
+        Ok(ids)
+    }
 
-        let result_iter = stmt.query_map([id], |row| {
-            Ok(ScrapingResult {
-                id: Some(row.get(0)?),
-                job_id: row.get(1)?,
-                scraped_data: row.get(2)?,
-                timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
-                success: row.get(4)?,
-                error_message: row.get(5)?,
-            })
+    pub fn get_result_snapshots(&self, result_id: i64) -> Result<Vec<PageSnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.kind, s.url, s.status, s.headers, b.body
+             FROM result_snapshots s JOIN snapshot_bodies b ON b.hash = s.body_hash
+             WHERE s.result_id = ?1 ORDER BY s.ordinal"
+        )?;
+        let rows = stmt.query_map([result_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, u16>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Vec<u8>>(4)?,
+            ))
         })?;
 
-        for result in result_iter {
-            return Ok(Some(result?));
+        let mut pages = Vec::new();
+        for row in rows {
+            let (kind, url, status, headers, body) = row?;
+            pages.push(PageSnapshot {
+                kind: kind.parse()?,
+                url,
+                status,
+                headers: serde_json::from_str(&headers)?,
+                body: snapshots::decompress(&body)?,
+            });
+        }
+        Ok(pages)
+    }
+
+    fn load_items(&self, result: &mut ScrapingResult) -> Result<()> {
+        let result_id = match result.id {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let mut stmt = self.conn.prepare(
+            "SELECT ordinal, field_name, value FROM result_items 
+             WHERE result_id = ?1 ORDER BY ordinal, id"
+        )?;
+        let item_iter = stmt.query_map([result_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut items = Vec::new();
+        let mut records: Vec<ScrapedRecord> = Vec::new();
+        let mut current_ordinal = None;
+        for item in item_iter {
+            let (ordinal, field_name, value) = item?;
+            match field_name {
+                Some(field_name) => {
+                    if current_ordinal != Some(ordinal) {
+                        records.push(ScrapedRecord::new());
+                        current_ordinal = Some(ordinal);
+                    }
+                    if let Some(record) = records.last_mut() {
+                        record.insert(field_name, value);
+                    }
+                }
+                None => items.push(value),
+            }
+        }
+
+        result.items = items;
+        result.records = records;
+        result.scraped_data = result.item_values().join("\n");
+        Ok(())
+    }
+
+    pub fn get_result(&self, id: i64) -> Result<Option<ScrapingResult>> {
+        let result = self.conn.query_row(
+            &format!("SELECT {} FROM results WHERE id = ?1", RESULT_COLUMNS),
+            [id],
+            row_to_result,
+        ).optional()?;
+
+        match result {
+            Some(mut result) => {
+                self.load_items(&mut result)?;
+                Ok(Some(result))
+            }
+            None => Ok(None),
         }
-        Ok(None)
     }
 
     pub fn get_results_for_job(&self, job_id: i64, limit: Option<i64>) -> Result<Vec<ScrapingResult>> {
         let query = if let Some(limit) = limit {
             format!(
-                "SELECT id, job_id, scraped_data, timestamp, success, error_message 
-                 FROM results WHERE job_id = ?1 ORDER BY timestamp DESC LIMIT {}",
-                limit
+                "SELECT {} FROM results WHERE job_id = ?1 ORDER BY timestamp DESC LIMIT {}",
+                RESULT_COLUMNS, limit
             )
         } else {
-            "SELECT id, job_id, scraped_data, timestamp, success, error_message 
-             FROM results WHERE job_id = ?1 ORDER BY timestamp DESC".to_string()
+            format!("SELECT {} FROM results WHERE job_id = ?1 ORDER BY timestamp DESC", RESULT_COLUMNS)
         };
 
         let mut stmt = self.conn.prepare(&query)?;
-        let result_iter = stmt.query_map([job_id], |row| {
-            Ok(ScrapingResult {
-                id: Some(row.get(0)?),
-                job_id: row.get(1)?,
-                scraped_data: row.get(2)?,
-                timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
-                success: row.get(4)?,
-                error_message: row.get(5)?,
-            })
-        })?;
+        let result_iter = stmt.query_map([job_id], row_to_result)?;
 
         let mut results = Vec::new();
         for result in result_iter {
-            results.push(result?);
+            let mut result = result?;
+            self.load_items(&mut result)?;
+            results.push(result);
         }
         Ok(results)
     }
 
+    pub fn get_last_successful_result(&self, job_id: i64) -> Result<Option<ScrapingResult>> {
+        let result = self.conn.query_row(
+            &format!(
+                "SELECT {} FROM results WHERE job_id = ?1 AND success = 1 
+                 ORDER BY timestamp DESC, id DESC LIMIT 1",
+                RESULT_COLUMNS
+            ),
+            [job_id],
+            row_to_result,
+        ).optional()?;
+
+        match result {
+            Some(mut result) => {
+                self.load_items(&mut result)?;
+                Ok(Some(result))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The last successful run of the job before `result`.
+    pub fn get_last_successful_result_before(&self, job_id: i64, result: &ScrapingResult) -> Result<Option<ScrapingResult>> {
+        let previous = self.conn.query_row(
+            &format!(
+                "SELECT {} FROM results WHERE job_id = ?1 AND success = 1
+                   AND (timestamp < ?2 OR (timestamp = ?2 AND id < ?3))
+                 ORDER BY timestamp DESC, id DESC LIMIT 1",
+                RESULT_COLUMNS
+            ),
+            params![job_id, result.timestamp.to_rfc3339(), result.id.unwrap_or(i64::MAX)],
+            row_to_result,
+        ).optional()?;
+
+        match previous {
+            Some(mut previous) => {
+                self.load_items(&mut previous)?;
+                Ok(Some(previous))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn get_job_stats(&self) -> Result<JobStats> {
         let total_jobs: i64 = self.conn.query_row(
             "SELECT COUNT(*) FROM jobs",
@@ -320,14 +422,47 @@ impl Database {
             }
         ).ok();
 
+        let mut degraded_job_ids = Vec::new();
+        for job in self.get_active_jobs()? {
+            let Some(job_id) = job.id else {
+                continue;
+            };
+            let history = self.get_run_history(job_id, health::HEALTH_WINDOW)?;
+            if health::assess(job_id, &history).status == HealthStatus::Degraded {
+                degraded_job_ids.push(job_id);
+            }
+        }
+
         Ok(JobStats {
             total_jobs,
             active_jobs,
             total_results,
             last_run,
+            degraded_jobs: degraded_job_ids.len() as i64,
+            degraded_job_ids,
         })
     }
 
+    /// The job's most recent runs, newest first.
+    pub fn get_run_history(&self, job_id: i64, limit: usize) -> Result<Vec<RunSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.timestamp, r.success, r.failure_reason,
+                    (SELECT COUNT(DISTINCT ordinal) FROM result_items WHERE result_id = r.id)
+             FROM results r WHERE r.job_id = ?1
+             ORDER BY r.timestamp DESC, r.id DESC LIMIT ?2"
+        )?;
+        let runs = stmt.query_map(params![job_id, limit as i64], |row| {
+            Ok(RunSummary {
+                timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(0)?).unwrap().with_timezone(&Utc),
+                success: row.get(1)?,
+                failure_reason: row.get::<_, Option<String>>(2)?.and_then(|reason| reason.parse().ok()),
+                item_count: row.get::<_, i64>(3)? as usize,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(runs)
+    }
+
     pub fn save_email_config(&self, config: &EmailConfig) -> Result<()> {
         self.conn.execute(
             r#"
@@ -371,4 +506,352 @@ impl Database {
         }
         Ok(None)
     }
-}
\ No newline at end of file
+
+    pub fn save_scraper_settings(&self, settings: &ScraperSettings) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO scraper_settings 
+            (id, max_concurrent_requests, max_concurrent_per_host, min_host_delay_ms, respect_robots_txt)
+            VALUES (1, ?1, ?2, ?3, ?4)
+            "#,
+            params![
+                settings.max_concurrent_requests,
+                settings.max_concurrent_per_host,
+                settings.min_host_delay_ms,
+                settings.respect_robots_txt
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_scraper_settings(&self) -> Result<ScraperSettings> {
+        let settings = self.conn.query_row(
+            "SELECT max_concurrent_requests, max_concurrent_per_host, min_host_delay_ms, respect_robots_txt 
+             FROM scraper_settings WHERE id = 1",
+            [],
+            |row| {
+                Ok(ScraperSettings {
+                    max_concurrent_requests: row.get(0)?,
+                    max_concurrent_per_host: row.get(1)?,
+                    min_host_delay_ms: row.get(2)?,
+                    respect_robots_txt: row.get(3)?,
+                })
+            },
+        ).optional()?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn get_cookie_jar(&self, job_id: i64) -> Result<CookieJar> {
+        let mut stmt = self.conn.prepare("SELECT cookies FROM cookie_jars WHERE job_id = ?1")?;
+        let mut rows = stmt.query_map([job_id], |row| parse_json_column(row, 0))?;
+        match rows.next() {
+            Some(jar) => Ok(jar?),
+            None => Ok(CookieJar::default()),
+        }
+    }
+
+    pub fn save_cookie_jar(&self, job_id: i64, jar: &CookieJar) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cookie_jars (job_id, cookies, updated_at) VALUES (?1, ?2, ?3)",
+            params![job_id, serde_json::to_string(jar)?, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_cookie_jar(&self, job_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM cookie_jars WHERE job_id = ?1", [job_id])?;
+        Ok(())
+    }
+}
+
+fn parse_json_column<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, idx: usize) -> SqliteResult<T> {
+    let raw: String = row.get(idx)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn insert_items(conn: &Connection, result_id: i64, items: &[String], records: &[ScrapedRecord]) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO result_items (result_id, ordinal, field_name, value) VALUES (?1, ?2, ?3, ?4)"
+    )?;
+    for (ordinal, value) in items.iter().enumerate() {
+        stmt.execute(params![result_id, ordinal as i64, None::<String>, value])?;
+    }
+    for (ordinal, record) in records.iter().enumerate() {
+        for (field_name, value) in record {
+            stmt.execute(params![result_id, ordinal as i64, field_name, value])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_snapshots(conn: &Connection, result_id: i64, pages: &[PageSnapshot]) -> Result<()> {
+    let mut insert_body = conn.prepare(
+        "INSERT OR IGNORE INTO snapshot_bodies (hash, body, size) VALUES (?1, ?2, ?3)"
+    )?;
+    let mut insert_snapshot = conn.prepare(
+        "INSERT INTO result_snapshots (result_id, ordinal, kind, url, status, headers, body_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+    )?;
+    for (ordinal, snapshot) in pages.iter().enumerate() {
+        let hash = snapshots::body_hash(&snapshot.body);
+        // Compressing is skipped for bodies that are already stored
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM snapshot_bodies WHERE hash = ?1)",
+            [&hash],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            insert_body.execute(params![hash, snapshots::compress(&snapshot.body)?, snapshot.body.len() as i64])?;
+        }
+        insert_snapshot.execute(params![
+            result_id,
+            ordinal as i64,
+            snapshot.kind.to_string(),
+            snapshot.url,
+            snapshot.status,
+            serde_json::to_string(&snapshot.headers)?,
+            hash
+        ])?;
+    }
+    Ok(())
+}
+
+fn parse_optional_json_column<T: serde::de::DeserializeOwned>(row: &rusqlite::Row, idx: usize) -> SqliteResult<Option<T>> {
+    let raw: Option<String> = row.get(idx)?;
+    raw.map(|raw| {
+        serde_json::from_str(&raw).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+        })
+    })
+    .transpose()
+}
+
+fn parse_optional_timestamp(row: &rusqlite::Row, idx: usize) -> SqliteResult<Option<DateTime<Utc>>> {
+    let raw: Option<String> = row.get(idx)?;
+    Ok(raw
+        .and_then(|raw| DateTime::parse_from_rfc3339(&raw).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc)))
+}
+
+fn row_to_job(row: &rusqlite::Row) -> SqliteResult<ScrapingJob> {
+    Ok(ScrapingJob {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        url: row.get(2)?,
+        selector_type: row.get::<_, String>(3)?.parse().unwrap(),
+        selector: row.get(4)?,
+        data_type: row.get::<_, String>(5)?.parse().unwrap(),
+        schedule: row.get(6)?,
+        user_agent: row.get(7)?,
+        proxy_url: row.get(8)?,
+        is_active: row.get(9)?,
+        created_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?).unwrap().with_timezone(&Utc)),
+        updated_at: Some(DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?).unwrap().with_timezone(&Utc)),
+        item_selector: row.get(12)?,
+        fields: parse_json_column(row, 13)?,
+        alert_rules: parse_json_column(row, 14)?,
+        retry_policy: parse_optional_json_column(row, 15)?.unwrap_or_default(),
+        accept_invalid_certs: row.get(16)?,
+        request: parse_optional_json_column(row, 17)?.unwrap_or_default(),
+        login: parse_optional_json_column(row, 18)?,
+        pagination: parse_optional_json_column(row, 19)?,
+        crawl: parse_optional_json_column(row, 20)?,
+        url_options: parse_optional_json_column(row, 21)?.unwrap_or_default(),
+        transforms: parse_optional_json_column(row, 22)?.unwrap_or_default(),
+        validation: parse_optional_json_column(row, 23)?.unwrap_or_default(),
+        store_snapshots: row.get(24)?,
+        locale: row.get(25)?,
+    })
+}
+
+fn row_to_result(row: &rusqlite::Row) -> SqliteResult<ScrapingResult> {
+    Ok(ScrapingResult {
+        id: Some(row.get(0)?),
+        job_id: row.get(1)?,
+        scraped_data: String::new(),
+        items: Vec::new(),
+        records: Vec::new(),
+        timestamp: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?).unwrap().with_timezone(&Utc),
+        success: row.get(3)?,
+        error_message: row.get(4)?,
+        content_hash: row.get(5)?,
+        changed: row.get(6)?,
+        diff: parse_optional_json_column(row, 7)?,
+        started_at: parse_optional_timestamp(row, 8)?,
+        finished_at: parse_optional_timestamp(row, 9)?,
+        duration_ms: row.get(10)?,
+        http_status: row.get(11)?,
+        bytes_fetched: row.get(12)?,
+        trigger: row.get::<_, String>(13)?.parse().unwrap_or_default(),
+        attempts: parse_json_column(row, 14)?,
+        failure_reason: row.get::<_, Option<String>>(15)?.and_then(|reason| reason.parse().ok()),
+        snapshots: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_job() -> ScrapingJob {
+        ScrapingJob {
+            name: "Sample".to_string(),
+            url: "https://example.com".to_string(),
+            selector: "p".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_result_items_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let job_id = db.create_job(&sample_job()).unwrap();
+
+        let mut record = ScrapedRecord::new();
+        record.insert("title".to_string(), "Widget".to_string());
+        record.insert("price".to_string(), "$10".to_string());
+
+        let result = ScrapingResult {
+            id: None,
+            job_id,
+            scraped_data: String::new(),
+            items: vec!["line one\nstill line one".to_string(), "second".to_string()],
+            records: vec![record.clone(), record],
+            timestamp: Utc::now(),
+            success: true,
+            error_message: None,
+            ..Default::default()
+        };
+        let result_id = db.save_result(&result).unwrap();
+
+        let loaded = db.get_result(result_id).unwrap().unwrap();
+        assert_eq!(loaded.items, result.items);
+        assert_eq!(loaded.records, result.records);
+        let record_json = r#"{"price":"$10","title":"Widget"}"#;
+        assert_eq!(loaded.scraped_data, format!("{}\n{}", record_json, record_json));
+
+        let history = db.get_results_for_job(job_id, Some(10)).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].items.len(), 2);
+    }
+
+    #[test]
+    fn test_snapshots_are_shared_between_runs() {
+        let db = Database::new(":memory:").unwrap();
+        let job_id = db.create_job(&sample_job()).unwrap();
+
+        let page = PageSnapshot {
+            kind: SnapshotKind::Page,
+            url: "https://example.com/".to_string(),
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/html".to_string())],
+            body: "<p>Widget</p>".to_string(),
+        };
+        let result = ScrapingResult {
+            job_id,
+            timestamp: Utc::now(),
+            success: true,
+            snapshots: vec![page.clone()],
+            ..Default::default()
+        };
+        let first = db.save_result(&result).unwrap();
+        let second = db.save_result(&result).unwrap();
+        db.save_result(&ScrapingResult { snapshots: Vec::new(), ..result }).unwrap();
+
+        assert_eq!(db.get_result_snapshots(first).unwrap(), vec![page]);
+        assert_eq!(db.get_result_ids_with_snapshots(job_id).unwrap(), vec![first, second]);
+        let bodies: i64 = db.conn.query_row("SELECT COUNT(*) FROM snapshot_bodies", [], |row| row.get(0)).unwrap();
+        assert_eq!(bodies, 1);
+
+        db.delete_job(job_id).unwrap();
+        let bodies: i64 = db.conn.query_row("SELECT COUNT(*) FROM snapshot_bodies", [], |row| row.get(0)).unwrap();
+        assert_eq!(bodies, 0);
+    }
+
+    #[test]
+    fn test_delete_job_removes_child_rows() {
+        let db = Database::new(":memory:").unwrap();
+        let job_id = db.create_job(&sample_job()).unwrap();
+        let other_id = db.create_job(&ScrapingJob { name: "Other Job".to_string(), ..sample_job() }).unwrap();
+
+        let result = ScrapingResult {
+            job_id,
+            timestamp: Utc::now(),
+            success: true,
+            items: vec!["Widget".to_string()],
+            ..Default::default()
+        };
+        db.save_result(&result).unwrap();
+        db.save_result(&ScrapingResult { job_id: other_id, ..result }).unwrap();
+        db.save_cookie_jar(job_id, &CookieJar::default()).unwrap();
+
+        db.delete_job(job_id).unwrap();
+
+        let count = |sql: &str| -> i64 { db.conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM results"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM result_items"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM cookie_jars"), 0);
+        assert!(db.get_run_history(job_id, 10).unwrap().is_empty());
+        assert_eq!(db.get_run_history(other_id, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_run_history() {
+        let db = Database::new(":memory:").unwrap();
+        let job_id = db.create_job(&sample_job()).unwrap();
+
+        let mut record = ScrapedRecord::new();
+        record.insert("title".to_string(), "Widget".to_string());
+        record.insert("price".to_string(), "$10".to_string());
+        let older = ScrapingResult {
+            job_id,
+            timestamp: Utc::now() - chrono::Duration::hours(1),
+            success: true,
+            records: vec![record.clone(), record],
+            ..Default::default()
+        };
+        let newer = ScrapingResult {
+            job_id,
+            timestamp: Utc::now(),
+            success: false,
+            failure_reason: Some(FailureReason::ValidationFailed),
+            ..Default::default()
+        };
+        db.save_result(&older).unwrap();
+        db.save_result(&newer).unwrap();
+
+        let history = db.get_run_history(job_id, 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].failure_reason, Some(FailureReason::ValidationFailed));
+        assert_eq!(history[0].item_count, 0);
+        assert_eq!(history[1].item_count, 2);
+        assert_eq!(db.get_run_history(job_id, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cookie_jar_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let job_id = db.create_job(&sample_job()).unwrap();
+        assert_eq!(db.get_cookie_jar(job_id).unwrap(), CookieJar::default());
+
+        let jar = CookieJar {
+            cookies: vec![crate::session::StoredCookie {
+                name: "session".to_string(),
+                value: "abc".to_string(),
+                domain: "example.com".to_string(),
+                host_only: true,
+                path: "/".to_string(),
+                secure: true,
+                expires_at: None,
+            }],
+        };
+        db.save_cookie_jar(job_id, &jar).unwrap();
+        assert_eq!(db.get_cookie_jar(job_id).unwrap(), jar);
+
+        db.delete_cookie_jar(job_id).unwrap();
+        assert!(db.get_cookie_jar(job_id).unwrap().cookies.is_empty());
+    }
+}
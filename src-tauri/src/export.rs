@@ -44,11 +44,16 @@ impl ExportService {
             results.retain(|result| result.timestamp <= *end_date);
         }
         
+        if request.changes_only {
+            results.retain(|result| result.changed == Some(true));
+        }
+        
         if results.is_empty() {
             return Err(anyhow!("No results found for the specified criteria"));
         }
         
         let file_path = match request.format {
+            ExportFormat::CSV if request.changes_only => self.export_changes_to_csv(&job, &results).await?,
             ExportFormat::CSV => self.export_to_csv(&job, &results).await?,
             ExportFormat::JSON => self.export_to_json(&job, &results).await?,
             ExportFormat::HTML => self.export_to_pdf(&job, &results).await?,
@@ -91,20 +96,59 @@ impl ExportService {
             .map_err(|e| anyhow!("Failed to create CSV file: {}", e))?;
         
         // Write header
-        writer.write_record(&["ID", "Job Name", "Scraped Data", "Timestamp", "Success", "Error Message"])
+        let columns = record_columns(job, results);
+        writer.write_record(csv_header(columns.as_deref()))
             .map_err(|e| anyhow!("Failed to write CSV header: {}", e))?;
         
         // Write data rows
         for result in results {
-            writer.write_record(&[
-                result.id.map(|id| id.to_string()).unwrap_or_default(),
-                job.name.clone(),
-                result.scraped_data.clone(),
-                result.timestamp.to_rfc3339(),
-                result.success.to_string(),
-                result.error_message.clone().unwrap_or_default(),
-            ])
-            .map_err(|e| anyhow!("Failed to write CSV row: {}", e))?;
+            for row in csv_rows(job, columns.as_deref(), result) {
+                writer.write_record(&row)
+                    .map_err(|e| anyhow!("Failed to write CSV row: {}", e))?;
+            }
+        }
+        
+        writer.flush()
+            .map_err(|e| anyhow!("Failed to flush CSV file: {}", e))?;
+        
+        Ok(file_path)
+    }
+    
+    async fn export_changes_to_csv(
+        &self,
+        job: &ScrapingJob,
+        results: &[ScrapingResult],
+    ) -> Result<PathBuf> {
+        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
+        let filename = format!("{}_{}_changes.csv", sanitize_filename(&job.name), timestamp);
+        let file_path = self.export_dir.join(filename);
+        
+        let mut writer = Writer::from_path(&file_path)
+            .map_err(|e| anyhow!("Failed to create CSV file: {}", e))?;
+        
+        // Write header
+        writer.write_record(["ID", "Job Name", "Timestamp", "Change", "Value"])
+            .map_err(|e| anyhow!("Failed to write CSV header: {}", e))?;
+        
+        // Write one row per added or removed item
+        for result in results {
+            let diff = match &result.diff {
+                Some(diff) => diff,
+                None => continue,
+            };
+            let changes = diff.added.iter().map(|value| ("added", value))
+                .chain(diff.removed.iter().map(|value| ("removed", value)));
+            
+            for (change, value) in changes {
+                writer.write_record(&[
+                    result.id.map(|id| id.to_string()).unwrap_or_default(),
+                    job.name.clone(),
+                    result.timestamp.to_rfc3339(),
+                    change.to_string(),
+                    value.clone(),
+                ])
+                .map_err(|e| anyhow!("Failed to write CSV row: {}", e))?;
+            }
         }
         
         writer.flush()
@@ -141,7 +185,7 @@ impl ExportService {
         let failed_results = results.len() - successful_results;
         
         let export_data = ExportData {
-            job: job.clone(),
+            job: job.redacted(),
             results: results.to_vec(),
             export_info: ExportInfo {
                 exported_at: Utc::now(),
@@ -473,19 +517,15 @@ impl ExportService {
             .map_err(|e| anyhow!("Failed to create CSV file: {}", e))?;
         
         // Write header
-        writer.write_record(&["ID", "Job Name", "Scraped Data", "Timestamp", "Success", "Error Message"])
+        let columns = record_columns(job, std::slice::from_ref(result));
+        writer.write_record(csv_header(columns.as_deref()))
             .map_err(|e| anyhow!("Failed to write CSV header: {}", e))?;
         
-        // Write data row
-        writer.write_record(&[
-            result.id.map(|id| id.to_string()).unwrap_or_default(),
-            job.name.clone(),
-            result.scraped_data.clone(),
-            result.timestamp.to_rfc3339(),
-            result.success.to_string(),
-            result.error_message.clone().unwrap_or_default(),
-        ])
-        .map_err(|e| anyhow!("Failed to write CSV row: {}", e))?;
+        // Write data rows
+        for row in csv_rows(job, columns.as_deref(), result) {
+            writer.write_record(&row)
+                .map_err(|e| anyhow!("Failed to write CSV row: {}", e))?;
+        }
         
         writer.flush()
             .map_err(|e| anyhow!("Failed to flush CSV file: {}", e))?;
@@ -517,7 +557,7 @@ impl ExportService {
         }
         
         let export_data = IndividualExportData {
-            job: job.clone(),
+            job: job.redacted(),
             result: result.clone(),
             export_info: IndividualExportInfo {
                 exported_at: Utc::now(),
@@ -818,6 +858,91 @@ fn sanitize_filename(name: &str) -> String {
         .to_string()
 }
 
+/// Field columns for jobs that extract records: the job's current fields,
+/// then any other field found in the results, so runs from before a field
+/// was renamed or removed keep their data. `None` for single-selector jobs.
+fn record_columns(job: &ScrapingJob, results: &[ScrapingResult]) -> Option<Vec<String>> {
+    if !job.has_fields() && results.iter().all(|result| result.records.is_empty()) {
+        return None;
+    }
+    
+    let mut columns = job.field_names();
+    for name in results.iter().flat_map(|result| &result.records).flat_map(|record| record.keys()) {
+        if !columns.contains(name) {
+            columns.push(name.clone());
+        }
+    }
+    Some(columns)
+}
+
+fn csv_header(columns: Option<&[String]>) -> Vec<String> {
+    match columns {
+        Some(columns) => {
+            let mut header: Vec<String> = ["ID", "Job Name", "Timestamp", "Success", "Error Message"]
+                .iter()
+                .map(|column| column.to_string())
+                .collect();
+            header.extend(columns.iter().cloned());
+            header
+        }
+        None => ["ID", "Job Name", "Scraped Data", "Timestamp", "Success", "Error Message"]
+            .iter()
+            .map(|column| column.to_string())
+            .collect(),
+    }
+}
+
+// One row per item, or per record with a column per field for field jobs.
+// A result without items still gets a single row so failures stay visible.
+fn csv_rows(job: &ScrapingJob, columns: Option<&[String]>, result: &ScrapingResult) -> Vec<Vec<String>> {
+    let id = result.id.map(|id| id.to_string()).unwrap_or_default();
+    let error_message = result.error_message.clone().unwrap_or_default();
+    
+    let Some(columns) = columns else {
+        let items: Vec<&str> = if result.items.is_empty() {
+            vec![""]
+        } else {
+            result.items.iter().map(|item| item.as_str()).collect()
+        };
+        
+        return items
+            .into_iter()
+            .map(|item| vec![
+                id.clone(),
+                job.name.clone(),
+                item.to_string(),
+                result.timestamp.to_rfc3339(),
+                result.success.to_string(),
+                error_message.clone(),
+            ])
+            .collect();
+    };
+    
+    let empty = ScrapedRecord::new();
+    let records: Vec<&ScrapedRecord> = if result.records.is_empty() {
+        vec![&empty]
+    } else {
+        result.records.iter().collect()
+    };
+    
+    records
+        .into_iter()
+        .map(|record| {
+            let mut row = vec![
+                id.clone(),
+                job.name.clone(),
+                result.timestamp.to_rfc3339(),
+                result.success.to_string(),
+                error_message.clone(),
+            ];
+            row.extend(columns.iter().map(|name| {
+                record.get(name).cloned().unwrap_or_default()
+            }));
+            row
+        })
+        .collect()
+}
+
 fn generate_table_rows(results: &[ScrapingResult]) -> String {
     results
         .iter()
@@ -874,4 +999,131 @@ mod tests {
         assert_eq!(export_service.get_export_directory(), temp_dir.path());
         assert!(temp_dir.path().exists());
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_json_export_leaves_out_secrets() {
+        let temp_dir = TempDir::new().unwrap();
+        let export_service = ExportService::new(temp_dir.path()).unwrap();
+        
+        let mut job = ScrapingJob {
+            id: Some(1),
+            name: "Account".to_string(),
+            login: Some(LoginSpec { username: "alice@example.com".to_string(), ..Default::default() }),
+            ..Default::default()
+        };
+        job.request.headers.insert("Authorization".to_string(), "Bearer s3cr3t".to_string());
+        job.request.headers.insert("Accept-Language".to_string(), "de".to_string());
+        job.request.cookies.insert("session".to_string(), "abc123".to_string());
+        
+        let path = export_service.export_to_json(&job, &[]).await.unwrap();
+        let content = fs::read_to_string(path).unwrap();
+        assert!(!content.contains("s3cr3t"));
+        assert!(!content.contains("abc123"));
+        assert!(!content.contains("alice@example.com"));
+        assert!(content.contains("\"Accept-Language\": \"de\""));
+    }
+    
+    #[test]
+    fn test_csv_rows_for_field_jobs() {
+        let job = ScrapingJob {
+            id: Some(1),
+            name: "Products".to_string(),
+            url: "https://example.com".to_string(),
+            item_selector: Some("li.product".to_string()),
+            fields: vec![
+                FieldSpec {
+                    name: "title".to_string(),
+                    selector_type: SelectorType::CSS,
+                    selector: "h2".to_string(),
+                    data_type: DataType::Text,
+                    transforms: Vec::new(),
+                },
+                FieldSpec {
+                    name: "price".to_string(),
+                    selector_type: SelectorType::CSS,
+                    selector: ".price".to_string(),
+                    data_type: DataType::Text,
+                    transforms: Vec::new(),
+                },
+            ],
+            ..Default::default()
+        };
+        
+        let mut record = ScrapedRecord::new();
+        record.insert("title".to_string(), "Widget".to_string());
+        record.insert("price".to_string(), "$10".to_string());
+        
+        let result = ScrapingResult {
+            id: Some(7),
+            job_id: 1,
+            scraped_data: String::new(),
+            items: Vec::new(),
+            records: vec![record],
+            timestamp: Utc::now(),
+            success: true,
+            error_message: None,
+            ..Default::default()
+        };
+        
+        let columns = record_columns(&job, std::slice::from_ref(&result));
+        let header = csv_header(columns.as_deref());
+        assert_eq!(&header[5..], ["title", "price"]);
+        
+        let rows = csv_rows(&job, columns.as_deref(), &result);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].len(), header.len());
+        assert_eq!(&rows[0][5..], ["Widget", "$10"]);
+        
+        // After "price" is renamed, older runs still export their prices
+        let renamed = ScrapingJob {
+            fields: vec![job.fields[0].clone(), FieldSpec { name: "cost".to_string(), ..job.fields[1].clone() }],
+            ..job
+        };
+        let columns = record_columns(&renamed, std::slice::from_ref(&result));
+        assert_eq!(csv_header(columns.as_deref())[5..], ["title", "cost", "price"]);
+        assert_eq!(csv_rows(&renamed, columns.as_deref(), &result)[0][5..], ["Widget", "", "$10"]);
+    }
+    
+    #[test]
+    fn test_csv_rows_for_crawl_jobs() {
+        let job = ScrapingJob {
+            id: Some(1),
+            name: "Products".to_string(),
+            url: "https://example.com".to_string(),
+            fields: vec![FieldSpec {
+                name: "title".to_string(),
+                selector_type: SelectorType::CSS,
+                selector: "h1".to_string(),
+                data_type: DataType::Text,
+                transforms: Vec::new(),
+            }],
+            crawl: Some(CrawlSpec {
+                link_selector: "a.product".to_string(),
+                url_field: Some("url".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        
+        let mut record = ScrapedRecord::new();
+        record.insert("title".to_string(), "Widget".to_string());
+        record.insert("url".to_string(), "https://example.com/widget".to_string());
+        
+        let result = ScrapingResult {
+            id: Some(7),
+            job_id: 1,
+            records: vec![record],
+            timestamp: Utc::now(),
+            success: true,
+            ..Default::default()
+        };
+        
+        let columns = record_columns(&job, std::slice::from_ref(&result));
+        let header = csv_header(columns.as_deref());
+        assert_eq!(&header[5..], ["url", "title"]);
+        
+        let rows = csv_rows(&job, columns.as_deref(), &result);
+        assert_eq!(rows[0].len(), header.len());
+        assert_eq!(&rows[0][5..], ["https://example.com/widget", "Widget"]);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::models::{FailureReason, HealthStatus, JobHealth, RunSummary, ScrapingJob};
+
+// A job's health is judged from its recent runs, newest first. Only runs
+// that fetched the page say anything about the selectors: network, HTTP,
+// robots.txt and login failures are counted in the success rate but not in
+// the item counts.
+
+/// Runs looked at when assessing a job.
+pub const HEALTH_WINDOW: usize = 20;
+/// Completed runs in a row without items before a job counts as degraded.
+const MAX_CONSECUTIVE_EMPTY: u32 = 3;
+/// A latest item count below this share of the baseline counts as a drop.
+const DROP_RATIO: f64 = 0.5;
+/// Earlier completed runs needed before a drop can be judged.
+const MIN_BASELINE_RUNS: usize = 3;
+
+pub fn assess(job_id: i64, history: &[RunSummary]) -> JobHealth {
+    let history = &history[..history.len().min(HEALTH_WINDOW)];
+    let mut health = JobHealth {
+        job_id,
+        status: HealthStatus::Unknown,
+        score: None,
+        runs_considered: history.len(),
+        success_rate: None,
+        latest_items: None,
+        baseline_items: None,
+        consecutive_empty: 0,
+        reasons: Vec::new(),
+    };
+    if history.is_empty() {
+        return health;
+    }
+
+    let successes = history.iter().filter(|run| run.success).count();
+    let success_rate = successes as f64 / history.len() as f64;
+    health.success_rate = Some(success_rate);
+
+    let counts: Vec<usize> = history.iter().filter(|run| is_completed(run)).map(|run| run.item_count).collect();
+    health.latest_items = counts.first().copied();
+    health.consecutive_empty = counts.iter().take_while(|count| **count == 0).count() as u32;
+    if counts.len() > MIN_BASELINE_RUNS {
+        health.baseline_items = Some(median(&counts[1..]));
+    }
+
+    if health.consecutive_empty >= MAX_CONSECUTIVE_EMPTY {
+        health.reasons.push(format!("the last {} completed runs found no items", health.consecutive_empty));
+    }
+    let count_ratio = match (health.latest_items, health.baseline_items) {
+        (Some(latest), Some(baseline)) if baseline > 0.0 => {
+            let ratio = latest as f64 / baseline;
+            if ratio < DROP_RATIO && health.consecutive_empty < MAX_CONSECUTIVE_EMPTY {
+                health.reasons.push(format!(
+                    "the last run found {} items, down from a typical {}",
+                    latest, baseline
+                ));
+            }
+            ratio.min(1.0)
+        }
+        (Some(0), _) => 0.0,
+        _ => 1.0,
+    };
+
+    health.score = Some((success_rate * 50.0 + count_ratio * 50.0).round() as u8);
+    health.status = if health.reasons.is_empty() { HealthStatus::Healthy } else { HealthStatus::Degraded };
+    health
+}
+
+/// The job's health if its latest run is the one that made it degraded.
+pub fn newly_degraded(job_id: i64, history: &[RunSummary]) -> Option<JobHealth> {
+    let now = assess(job_id, history);
+    let before = assess(job_id, history.get(1..).unwrap_or_default());
+    (now.status == HealthStatus::Degraded && before.status != HealthStatus::Degraded).then_some(now)
+}
+
+pub fn format_health_email(job: &ScrapingJob, health: &JobHealth) -> (String, String) {
+    let subject = format!("Web Scraping Health Warning: {} may be broken", job.name);
+
+    let mut body = format!(
+        "Hello,\n\nThe web scraping job '{}' looks degraded. The site may have changed and the job's selectors may need updating.\n\nJob Details:\n- Job Name: {}\n- URL: {}\n",
+        job.name, job.name, job.url
+    );
+    if let Some(score) = health.score {
+        body.push_str(&format!("- Health Score: {}/100\n", score));
+    }
+    if let Some(success_rate) = health.success_rate {
+        body.push_str(&format!(
+            "- Success Rate: {:.0}% over the last {} runs\n",
+            success_rate * 100.0, health.runs_considered
+        ));
+    }
+
+    body.push_str("\nReasons:\n");
+    for reason in &health.reasons {
+        body.push_str(&format!("  - {}\n", reason));
+    }
+
+    body.push_str("\nBest regards,\nAutomated Web Scraper");
+    (subject, body)
+}
+
+fn is_completed(run: &RunSummary) -> bool {
+    run.success || run.failure_reason == Some(FailureReason::ValidationFailed)
+}
+
+fn median(counts: &[usize]) -> f64 {
+    let mut sorted = counts.to_vec();
+    sorted.sort_unstable();
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) as f64 / 2.0
+    } else {
+        sorted[middle] as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn run(success: bool, item_count: usize) -> RunSummary {
+        RunSummary {
+            timestamp: Utc::now(),
+            success,
+            failure_reason: if success { None } else { Some(FailureReason::Network) },
+            item_count,
+        }
+    }
+
+    // Newest first
+    fn runs(counts: &[usize]) -> Vec<RunSummary> {
+        counts.iter().map(|count| run(true, *count)).collect()
+    }
+
+    #[test]
+    fn test_healthy_and_unknown() {
+        assert_eq!(assess(1, &[]).status, HealthStatus::Unknown);
+
+        let health = assess(1, &runs(&[20, 22, 19, 21, 20]));
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.baseline_items, Some(20.5));
+        assert_eq!(health.score, Some(99));
+    }
+
+    #[test]
+    fn test_sharp_drop_is_degraded() {
+        let health = assess(1, &runs(&[4, 20, 22, 19, 21]));
+        assert_eq!(health.status, HealthStatus::Degraded);
+        assert_eq!(health.reasons, vec!["the last run found 4 items, down from a typical 20.5"]);
+
+        // Transport failures don't count as empty runs
+        let mut history = vec![run(false, 0), run(false, 0)];
+        history.extend(runs(&[20, 22, 19, 21]));
+        let health = assess(1, &history);
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.success_rate, Some(4.0 / 6.0));
+    }
+
+    #[test]
+    fn test_consecutive_empty_runs_are_degraded() {
+        let history = runs(&[0, 0, 0, 10]);
+        let health = assess(1, &history);
+        assert_eq!(health.status, HealthStatus::Degraded);
+        assert_eq!(health.consecutive_empty, 3);
+        assert_eq!(health.reasons, vec!["the last 3 completed runs found no items"]);
+
+        // Only the run that crossed the line triggers an email
+        assert!(newly_degraded(1, &history).is_some());
+        let mut later = runs(&[0]);
+        later.extend(history);
+        assert!(newly_degraded(1, &later).is_none());
+    }
+}
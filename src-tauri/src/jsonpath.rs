@@ -0,0 +1,93 @@
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use crate::models::SelectorMatch;
+use anyhow::{Result, anyhow};
+
+pub fn parse_jsonpath(expression: &str) -> Result<JsonPath> {
+    JsonPath::parse(expression)
+        .map_err(|e| anyhow!("Invalid JSONPath expression '{}': {}", expression, e))
+}
+
+pub fn parse_json(body: &str) -> Result<Value> {
+    serde_json::from_str(body)
+        .map_err(|e| anyhow!("Response is not valid JSON: {}", e))
+}
+
+/// The nodes matched by `path`, in the order JSONPath returns them.
+pub fn query<'v>(path: &JsonPath, value: &'v Value) -> Vec<&'v Value> {
+    path.query(value).all()
+}
+
+/// Evaluates `expression` against a JSON document, one string per match.
+pub fn select_values(body: &str, expression: &str) -> Result<Vec<String>> {
+    let path = parse_jsonpath(expression)?;
+    let document = parse_json(body)?;
+
+    Ok(query(&path, &document)
+        .into_iter()
+        .map(value_to_string)
+        .filter(|value| !value.is_empty())
+        .collect())
+}
+
+/// Every match of `expression` with its normalized path, e.g.
+/// `$['products'][0]['name']`. Unlike `select_values`, nulls are kept.
+pub fn select_matches(body: &str, expression: &str) -> Result<Vec<SelectorMatch>> {
+    let path = parse_jsonpath(expression)?;
+    let document = parse_json(body)?;
+
+    Ok(path.query_located(&document)
+        .into_iter()
+        .map(|node| SelectorMatch {
+            path: node.location().to_string(),
+            value: value_to_string(node.node()),
+        })
+        .collect())
+}
+
+/// Strings are returned as-is, other scalars in their JSON form, and objects
+/// and arrays as compact JSON. `null` becomes an empty string.
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Bool(_) | Value::Number(_) | Value::Array(_) | Value::Object(_) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"{
+        "total": 2,
+        "products": [
+            {"name": "Widget", "price": 9.5, "tags": ["new"], "stock": null},
+            {"name": "Gadget", "price": 20, "tags": [], "stock": {"warehouse": 3}}
+        ]
+    }"#;
+
+    #[test]
+    fn test_select_values() {
+        assert_eq!(select_values(BODY, "$.products[*].name").unwrap(), vec!["Widget", "Gadget"]);
+        assert_eq!(select_values(BODY, "$.products[*].price").unwrap(), vec!["9.5", "20"]);
+        assert_eq!(select_values(BODY, "$.total").unwrap(), vec!["2"]);
+        assert_eq!(select_values(BODY, "$.products[*].stock").unwrap(), vec![r#"{"warehouse":3}"#]);
+        assert_eq!(select_values(BODY, "$.products[?@.price > 10].name").unwrap(), vec!["Gadget"]);
+    }
+
+    #[test]
+    fn test_select_matches() {
+        let matches = select_matches(BODY, "$.products[*].stock").unwrap();
+        assert_eq!(matches, vec![
+            SelectorMatch { path: "$['products'][0]['stock']".to_string(), value: String::new() },
+            SelectorMatch { path: "$['products'][1]['stock']".to_string(), value: r#"{"warehouse":3}"#.to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(parse_jsonpath("$.products[").is_err());
+        assert!(select_values("<html></html>", "$.a").is_err());
+    }
+}
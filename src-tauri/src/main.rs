@@ -3,6 +3,24 @@
 
 mod models;
 mod database;
+mod migrations;
+mod changes;
+mod alerts;
+mod numbers;
+mod retry;
+mod politeness;
+mod robots;
+mod session;
+mod pagination;
+mod xpath;
+mod jsonpath;
+mod metadata;
+mod transforms;
+mod validation;
+mod snapshots;
+mod suggest;
+mod health;
+mod credentials;
 mod scraper;
 mod scheduler;
 mod email;
@@ -17,7 +35,8 @@ use log::{info, error};
 use models::*;
 use database::Database;
 use scraper::WebScraper;
-use scheduler::ScrapingScheduler;
+use session::CookieJar;
+use scheduler::{JobRunner, ScrapingScheduler};
 use email::EmailService;
 use export::ExportService;
 
@@ -63,16 +82,29 @@ async fn initialize_app(app_handle: tauri::AppHandle) -> Result<String, String>
             .map_err(|e| format!("Failed to initialize database: {}", e))?
     ));
     
+    // Initialize email service with the saved configuration, if any
+    let mut email_service = EmailService::new();
+    if let Some(config) = db.lock().await.get_email_config()
+        .map_err(|e| format!("Failed to load email config: {}", e))? {
+        email_service.set_config(config);
+    }
+    let email_service = Arc::new(Mutex::new(email_service));
+    
+    // Initialize the scraper shared by test runs and the scheduler, so
+    // politeness limits apply across all jobs
+    let scraper = Arc::new(WebScraper::new());
+    let scraper_settings = db.lock().await.get_scraper_settings()
+        .map_err(|e| format!("Failed to load scraper settings: {}", e))?;
+    scraper.politeness().update_settings(scraper_settings);
+    
     // Initialize scheduler
     let scheduler = Arc::new(Mutex::new(
-        ScrapingScheduler::new(Arc::clone(&db))
+        ScrapingScheduler::new(Arc::clone(&db), Arc::clone(&scraper), Arc::clone(&email_service))
             .await
             .map_err(|e| format!("Failed to initialize scheduler: {}", e))?
     ));
     
     // Initialize other services
-    let scraper = Arc::new(WebScraper::new());
-    let email_service = Arc::new(Mutex::new(EmailService::new()));
     let export_service = Arc::new(
         ExportService::new(&export_dir)
             .map_err(|e| format!("Failed to initialize export service: {}", e))?
@@ -99,7 +131,10 @@ async fn initialize_app(app_handle: tauri::AppHandle) -> Result<String, String>
 }
 
 #[tauri::command]
-async fn create_job(job: ScrapingJob) -> Result<i64, String> {
+async fn create_job(mut job: ScrapingJob) -> Result<i64, String> {
+    validate_job_settings(&job)?;
+    let secrets = job.request.take_secrets(&RequestSecrets::default());
+    
     let state_guard = APP_STATE.lock().await;
     let state = state_guard.as_ref().ok_or("Application not initialized")?;
     
@@ -108,6 +143,9 @@ async fn create_job(job: ScrapingJob) -> Result<i64, String> {
         .map_err(|e| format!("Failed to create job: {}", e))?;
     drop(db);
     
+    credentials::set_job_request_secrets(job_id, &secrets)
+        .map_err(|e| format!("Failed to save request headers and cookies: {}", e))?;
+    
     // Schedule the job if it's active
     if job.is_active {
         let mut job_with_id = job;
@@ -143,7 +181,17 @@ async fn get_job(id: i64) -> Result<Option<ScrapingJob>, String> {
 }
 
 #[tauri::command]
-async fn update_job(job: ScrapingJob) -> Result<(), String> {
+async fn update_job(mut job: ScrapingJob) -> Result<(), String> {
+    validate_job_settings(&job)?;
+    let job_id = job.id.ok_or("Job must have an ID")?;
+    
+    // Values the user left as the placeholder keep their stored secret
+    let stored = credentials::get_job_request_secrets(job_id)
+        .map_err(|e| format!("Failed to load request headers and cookies: {}", e))?;
+    let secrets = job.request.take_secrets(&stored);
+    credentials::set_job_request_secrets(job_id, &secrets)
+        .map_err(|e| format!("Failed to save request headers and cookies: {}", e))?;
+    
     let state_guard = APP_STATE.lock().await;
     let state = state_guard.as_ref().ok_or("Application not initialized")?;
     
@@ -161,6 +209,17 @@ async fn update_job(job: ScrapingJob) -> Result<(), String> {
     Ok(())
 }
 
+/// Rejects transforms and validation rules that would only fail once the
+/// job runs.
+fn validate_job_settings(job: &ScrapingJob) -> Result<(), String> {
+    for transforms in std::iter::once(&job.transforms).chain(job.fields.iter().map(|field| &field.transforms)) {
+        transforms::validate(transforms)
+            .map_err(|e| format!("Transform validation failed: {}", e))?;
+    }
+    validation::validate_rules(&job.validation)
+        .map_err(|e| format!("Validation rules are invalid: {}", e))
+}
+
 #[tauri::command]
 async fn delete_job(id: i64) -> Result<(), String> {
     let state_guard = APP_STATE.lock().await;
@@ -175,22 +234,59 @@ async fn delete_job(id: i64) -> Result<(), String> {
     let db = state.db.lock().await;
     db.delete_job(id)
         .map_err(|e| format!("Failed to delete job: {}", e))?;
+    drop(db);
+    
+    if let Err(e) = credentials::delete_job_password(id) {
+        error!("Failed to delete stored password for job {}: {}", id, e);
+    }
+    if let Err(e) = credentials::delete_job_request_secrets(id) {
+        error!("Failed to delete stored request headers and cookies for job {}: {}", id, e);
+    }
     
     Ok(())
 }
 
 #[tauri::command]
-async fn test_scrape_job(job: ScrapingJob) -> Result<Vec<String>, String> {
+async fn test_scrape_job(job: ScrapingJob) -> Result<TestScrapePreview, String> {
     let state_guard = APP_STATE.lock().await;
     let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    let scraper = Arc::clone(&state.scraper);
     
-    state.scraper.test_scrape(&job)
+    // Saved jobs with a login test with their current session, read-only
+    let session = match (job.id, &job.login) {
+        (Some(job_id), Some(_)) => state.db.lock().await.get_cookie_jar(job_id)
+            .map_err(|e| format!("Failed to load job session: {}", e))?,
+        _ => CookieJar::default(),
+    };
+    drop(state_guard);
+    
+    scraper.test_scrape(&job, session)
         .await
         .map_err(|e| format!("Failed to test scrape: {}", e))
 }
 
 #[tauri::command]
 async fn run_job_now(id: i64) -> Result<Vec<String>, String> {
+    let (runner, job) = job_runner(id).await?;
+    
+    // Runs can take minutes with retries, so no app-wide lock is held here
+    runner.run_now(&job)
+        .await
+        .map_err(|e| format!("Failed to run job: {}", e))
+}
+
+#[tauri::command]
+async fn reextract_job_snapshots(job_id: i64) -> Result<BackfillSummary, String> {
+    let (runner, job) = job_runner(job_id).await?;
+    
+    runner.reextract_snapshots(&job)
+        .await
+        .map_err(|e| format!("Failed to re-extract snapshots: {}", e))
+}
+
+/// Loads the job and copies the runner out of the app state, releasing
+/// the state and scheduler locks before the caller starts the run.
+async fn job_runner(id: i64) -> Result<(JobRunner, ScrapingJob), String> {
     let state_guard = APP_STATE.lock().await;
     let state = state_guard.as_ref().ok_or("Application not initialized")?;
     
@@ -200,10 +296,8 @@ async fn run_job_now(id: i64) -> Result<Vec<String>, String> {
         .ok_or("Job not found")?;
     drop(db);
     
-    let scheduler = state.scheduler.lock().await;
-    scheduler.run_job_now(job)
-        .await
-        .map_err(|e| format!("Failed to run job: {}", e))
+    let runner = state.scheduler.lock().await.runner();
+    Ok((runner, job))
 }
 
 #[tauri::command]
@@ -216,6 +310,27 @@ async fn get_job_results(job_id: i64, limit: Option<i64>) -> Result<Vec<Scraping
         .map_err(|e| format!("Failed to get results: {}", e))
 }
 
+#[tauri::command]
+async fn get_job_changes(job_id: i64) -> Result<Option<ScrapingResult>, String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    let db = state.db.lock().await;
+    db.get_last_successful_result(job_id)
+        .map_err(|e| format!("Failed to get job changes: {}", e))
+}
+
+#[tauri::command]
+async fn get_job_health(job_id: i64) -> Result<JobHealth, String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    let db = state.db.lock().await;
+    let history = db.get_run_history(job_id, health::HEALTH_WINDOW)
+        .map_err(|e| format!("Failed to get run history: {}", e))?;
+    Ok(health::assess(job_id, &history))
+}
+
 #[tauri::command]
 async fn get_job_stats() -> Result<JobStats, String> {
     let state_guard = APP_STATE.lock().await;
@@ -316,6 +431,51 @@ async fn get_email_config() -> Result<Option<EmailConfig>, String> {
         .map_err(|e| format!("Failed to get email config: {}", e))
 }
 
+#[tauri::command]
+async fn save_scraper_settings(settings: ScraperSettings) -> Result<(), String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    politeness::validate_settings(&settings)
+        .map_err(|e| format!("Invalid scraper settings: {}", e))?;
+    
+    let db = state.db.lock().await;
+    db.save_scraper_settings(&settings)
+        .map_err(|e| format!("Failed to save scraper settings: {}", e))?;
+    drop(db);
+    
+    state.scraper.politeness().update_settings(settings);
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_scraper_settings() -> Result<ScraperSettings, String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    Ok(state.scraper.politeness().settings())
+}
+
+#[tauri::command]
+async fn set_job_password(job_id: i64, password: String) -> Result<(), String> {
+    credentials::set_job_password(job_id, &password)
+        .map_err(|e| format!("Failed to save password: {}", e))?;
+    
+    // Log in again with the new password on the next run
+    clear_job_session(job_id).await
+}
+
+#[tauri::command]
+async fn clear_job_session(job_id: i64) -> Result<(), String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    let db = state.db.lock().await;
+    db.delete_cookie_jar(job_id)
+        .map_err(|e| format!("Failed to clear job session: {}", e))
+}
+
 #[tauri::command]
 async fn test_email_connection() -> Result<(), String> {
     let state_guard = APP_STATE.lock().await;
@@ -357,6 +517,122 @@ async fn validate_css_selector(selector: String) -> Result<bool, String> {
         .map_err(|e| format!("CSS selector validation failed: {}", e))
 }
 
+#[tauri::command]
+async fn validate_xpath(expression: String) -> Result<bool, String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    state.scraper.validate_xpath(&expression)
+        .map_err(|e| format!("XPath validation failed: {}", e))
+}
+
+#[tauri::command]
+async fn validate_jsonpath(expression: String) -> Result<bool, String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    state.scraper.validate_jsonpath(&expression)
+        .map_err(|e| format!("JSONPath validation failed: {}", e))
+}
+
+#[tauri::command]
+async fn run_selector_playground(request: PlaygroundRequest) -> Result<PlaygroundResult, String> {
+    let state_guard = APP_STATE.lock().await;
+    let state = state_guard.as_ref().ok_or("Application not initialized")?;
+    
+    let body = load_playground_source(state, request.source).await?;
+    let matches = state.scraper.select_matches(&body, &request.selector_type, &request.selector, &request.data_type)
+        .map_err(|e| format!("Selector failed: {}", e))?;
+    Ok(PlaygroundResult { count: matches.len(), matches })
+}
+
+#[tauri::command]
+async fn suggest_selectors(source: SuggestionSource, example: String) -> Result<Vec<SelectorSuggestion>, String> {
+    let body = match source {
+        SuggestionSource::Job(job_id) => fetch_job_page(job_id).await?,
+        SuggestionSource::Document(source) => {
+            let state_guard = APP_STATE.lock().await;
+            let state = state_guard.as_ref().ok_or("Application not initialized")?;
+            load_playground_source(state, source).await?
+        }
+    };
+    Ok(suggest::suggest_selectors(&body, &example, 5))
+}
+
+/// Fetches a job's page the way its runs do, keeping any session cookies
+/// the site sets. The app state is not locked while the page is fetched.
+async fn fetch_job_page(job_id: i64) -> Result<String, String> {
+    let (db, scraper) = {
+        let state_guard = APP_STATE.lock().await;
+        let state = state_guard.as_ref().ok_or("Application not initialized")?;
+        (Arc::clone(&state.db), Arc::clone(&state.scraper))
+    };
+    
+    let db_guard = db.lock().await;
+    let job = db_guard.get_job(job_id)
+        .map_err(|e| format!("Failed to get job: {}", e))?
+        .ok_or("Job not found")?;
+    let mut session = match job.login {
+        Some(_) => db_guard.get_cookie_jar(job_id)
+            .map_err(|e| format!("Failed to load job session: {}", e))?,
+        None => CookieJar::default(),
+    };
+    drop(db_guard);
+    
+    let body = scraper.fetch_job_page(&job, &mut session)
+        .await
+        .map_err(|e| format!("Failed to fetch page: {}", e))?;
+    
+    if job.login.is_some() {
+        db.lock().await.save_cookie_jar(job_id, &session)
+            .map_err(|e| format!("Failed to save job session: {}", e))?;
+    }
+    Ok(body)
+}
+
+async fn load_playground_source(state: &AppState, source: PlaygroundSource) -> Result<String, String> {
+    match source {
+        PlaygroundSource::Html(html) => Ok(html),
+        PlaygroundSource::Snapshot { result_id, index } => {
+            let db = state.db.lock().await;
+            let mut snapshots = db.get_result_snapshots(result_id)
+                .map_err(|e| format!("Failed to load snapshots: {}", e))?;
+            if index >= snapshots.len() {
+                return Err(format!("Run {} has no stored page {}", result_id, index));
+            }
+            Ok(snapshots.swap_remove(index).body)
+        }
+        PlaygroundSource::File(path) => {
+            // Only files in the export directory can be opened from the
+            // webview; paths are resolved first so ".." and links can't
+            // point elsewhere
+            let export_dir = state.export_service.get_export_directory().canonicalize()
+                .map_err(|e| format!("Failed to resolve the export directory: {}", e))?;
+            let file_path = std::path::Path::new(&path).canonicalize()
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            if !file_path.starts_with(&export_dir) {
+                return Err("File is not within the export directory".to_string());
+            }
+            std::fs::read_to_string(&file_path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn validate_transforms(transforms: Vec<Transform>) -> Result<bool, String> {
+    transforms::validate(&transforms)
+        .map(|_| true)
+        .map_err(|e| format!("Transform validation failed: {}", e))
+}
+
+#[tauri::command]
+async fn validate_validation_rules(rules: ValidationRules) -> Result<bool, String> {
+    validation::validate_rules(&rules)
+        .map(|_| true)
+        .map_err(|e| format!("Validation rules are invalid: {}", e))
+}
+
 #[tauri::command]
 async fn validate_regex_pattern(pattern: String) -> Result<bool, String> {
     let state_guard = APP_STATE.lock().await;
@@ -515,16 +791,29 @@ fn main() {
             delete_job,
             test_scrape_job,
             run_job_now,
+            reextract_job_snapshots,
             get_job_results,
+            get_job_changes,
             get_job_stats,
+            get_job_health,
             export_job_results,
             export_individual_result,
             save_email_config,
             get_email_config,
+            save_scraper_settings,
+            get_scraper_settings,
+            set_job_password,
+            clear_job_session,
             test_email_connection,
             send_export_email,
             validate_url,
             validate_css_selector,
+            validate_xpath,
+            validate_jsonpath,
+            validate_transforms,
+            run_selector_playground,
+            suggest_selectors,
+            validate_validation_rules,
             validate_regex_pattern,
             validate_cron_expression,
             list_export_files,
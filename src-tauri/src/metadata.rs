@@ -0,0 +1,207 @@
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+use crate::scraper::collapse_whitespace;
+use log::warn;
+
+/// Structured metadata embedded in a page, as `(key, value)` pairs in
+/// document order. Keys look like:
+///
+/// - `Product.offers.price` for JSON-LD and microdata, starting with the
+///   schema.org type of the top-level item
+/// - `og:title`, `twitter:card` or `description` for `<meta>` tags
+///
+/// A key can occur more than once, e.g. for a list of offers.
+pub fn extract_metadata(document: &Html) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    collect_json_ld(document, &mut pairs);
+    collect_microdata(document, &mut pairs);
+    collect_meta_tags(document, &mut pairs);
+    pairs
+}
+
+/// All values stored under `key`. A `schema.org/` prefix is accepted so that
+/// keys can be copied from the vocabulary's documentation.
+pub fn values_for(pairs: &[(String, String)], key: &str) -> Vec<String> {
+    pairs
+        .iter()
+        .filter(|(name, _)| key_matches(name, key))
+        .map(|(_, value)| value.clone())
+        .collect()
+}
+
+/// Whether a pair's key `name` is the one `key` asks for.
+pub fn key_matches(name: &str, key: &str) -> bool {
+    name == normalize_key(key)
+}
+
+fn normalize_key(key: &str) -> &str {
+    let key = key.trim();
+    ["https://schema.org/", "http://schema.org/", "schema.org/"]
+        .iter()
+        .find_map(|prefix| key.strip_prefix(prefix))
+        .unwrap_or(key)
+}
+
+fn collect_json_ld(document: &Html, pairs: &mut Vec<(String, String)>) {
+    let selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    for script in document.select(&selector) {
+        let content = script.text().collect::<String>();
+        match serde_json::from_str::<Value>(&content) {
+            Ok(value) => collect_json_ld_items(&value, pairs),
+            Err(e) => warn!("Skipping invalid JSON-LD block: {}", e),
+        }
+    }
+}
+
+fn collect_json_ld_items(value: &Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect_json_ld_items(item, pairs);
+            }
+        }
+        Value::Object(object) => {
+            if let Some(graph) = object.get("@graph") {
+                collect_json_ld_items(graph, pairs);
+            }
+            if let Some(item_type) = json_ld_type(object.get("@type")) {
+                flatten_json(value, &item_type, pairs);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn json_ld_type(value: Option<&Value>) -> Option<String> {
+    let type_name = match value? {
+        Value::String(name) => name.as_str(),
+        Value::Array(names) => names.first()?.as_str()?,
+        _ => return None,
+    };
+    Some(normalize_key(type_name).to_string())
+}
+
+fn flatten_json(value: &Value, prefix: &str, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (name, child) in object {
+                if !name.starts_with('@') {
+                    flatten_json(child, &format!("{}.{}", prefix, name), pairs);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                flatten_json(item, prefix, pairs);
+            }
+        }
+        Value::String(text) => pairs.push((prefix.to_string(), text.trim().to_string())),
+        Value::Number(number) => pairs.push((prefix.to_string(), number.to_string())),
+        Value::Bool(flag) => pairs.push((prefix.to_string(), flag.to_string())),
+        Value::Null => {}
+    }
+}
+
+fn collect_microdata(document: &Html, pairs: &mut Vec<(String, String)>) {
+    // Items nested in another item's property are collected through it
+    let selector = Selector::parse("[itemscope]:not([itemprop])").unwrap();
+    for item in document.select(&selector) {
+        let item_type = item.value().attr("itemtype")
+            .and_then(|item_type| item_type.split_whitespace().next())
+            .map(|item_type| item_type.rsplit('/').next().unwrap_or(item_type).to_string())
+            .unwrap_or_else(|| "Thing".to_string());
+        collect_item_properties(item, &item_type, pairs);
+    }
+}
+
+fn collect_item_properties(scope: ElementRef, prefix: &str, pairs: &mut Vec<(String, String)>) {
+    for child in scope.children().filter_map(ElementRef::wrap) {
+        let element = child.value();
+        let is_scope = element.attr("itemscope").is_some();
+
+        if let Some(properties) = element.attr("itemprop") {
+            for property in properties.split_whitespace() {
+                let key = format!("{}.{}", prefix, property);
+                if is_scope {
+                    collect_item_properties(child, &key, pairs);
+                } else {
+                    pairs.push((key, microdata_value(child)));
+                }
+            }
+        }
+
+        if !is_scope {
+            collect_item_properties(child, prefix, pairs);
+        }
+    }
+}
+
+fn microdata_value(element: ElementRef) -> String {
+    let value = element.value();
+    let attribute = match value.name() {
+        _ if value.attr("content").is_some() => "content",
+        "a" | "link" | "area" => "href",
+        "img" | "audio" | "video" | "source" | "iframe" | "embed" => "src",
+        "object" => "data",
+        "time" if value.attr("datetime").is_some() => "datetime",
+        "meter" | "data" => "value",
+        _ => return collapse_whitespace(&element.text().collect::<Vec<_>>().join(" ")),
+    };
+    value.attr(attribute).unwrap_or_default().trim().to_string()
+}
+
+fn collect_meta_tags(document: &Html, pairs: &mut Vec<(String, String)>) {
+    let selector = Selector::parse("meta[content]").unwrap();
+    for meta in document.select(&selector) {
+        let element = meta.value();
+        // Microdata properties on <meta> were already collected with their item
+        if element.attr("itemprop").is_some() {
+            continue;
+        }
+        let Some(name) = element.attr("property").or_else(|| element.attr("name")) else {
+            continue;
+        };
+        pairs.push((name.trim().to_string(), element.attr("content").unwrap_or_default().trim().to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"
+        <html><head>
+            <meta property="og:title" content="Widget Pro">
+            <meta name="description" content="The best widget">
+            <script type="application/ld+json">
+                {"@context": "https://schema.org", "@type": "Product", "name": "Widget Pro",
+                 "offers": [{"@type": "Offer", "price": 19.99, "priceCurrency": "USD"},
+                            {"@type": "Offer", "price": 17.5, "priceCurrency": "USD"}]}
+            </script>
+            <script type="application/ld+json">not json</script>
+        </head><body>
+            <div itemscope itemtype="https://schema.org/Product">
+                <h1 itemprop="name">Widget Pro</h1>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <span itemprop="price" content="19.99">$19.99</span>
+                    <link itemprop="availability" href="https://schema.org/InStock">
+                </div>
+                <div><span itemprop="brand">  Acme
+                    Corp </span></div>
+            </div>
+        </body></html>
+    "#;
+
+    #[test]
+    fn test_extract_metadata() {
+        let pairs = extract_metadata(&Html::parse_document(HTML));
+
+        assert_eq!(values_for(&pairs, "og:title"), vec!["Widget Pro"]);
+        assert_eq!(values_for(&pairs, "description"), vec!["The best widget"]);
+        assert_eq!(values_for(&pairs, "schema.org/Product.offers.price"), vec!["19.99", "17.5", "19.99"]);
+        assert_eq!(values_for(&pairs, "Product.name"), vec!["Widget Pro", "Widget Pro"]);
+        assert_eq!(values_for(&pairs, "Product.offers.availability"), vec!["https://schema.org/InStock"]);
+        assert_eq!(values_for(&pairs, "Product.brand"), vec!["Acme Corp"]);
+        assert!(values_for(&pairs, "Product.missing").is_empty());
+    }
+}
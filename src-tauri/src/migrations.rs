@@ -0,0 +1,507 @@
+use rusqlite::{params, Connection, Result as SqliteResult};
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use log::info;
+
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    pub apply: fn(&Connection) -> Result<()>,
+}
+
+// Ordered schema steps. Each one runs in its own transaction and bumps
+// `PRAGMA user_version` to its version, so append new steps at the end and
+// never edit a step that has already shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create jobs, results and email_config tables",
+        apply: create_initial_tables,
+    },
+    Migration {
+        version: 2,
+        description: "add item selector and fields to jobs",
+        apply: add_job_fields,
+    },
+    Migration {
+        version: 3,
+        description: "store scraped values in result_items",
+        apply: create_result_items,
+    },
+    Migration {
+        version: 4,
+        description: "add change detection columns to results",
+        apply: add_change_detection,
+    },
+    Migration {
+        version: 5,
+        description: "add alert rules to jobs",
+        apply: add_alert_rules,
+    },
+    Migration {
+        version: 6,
+        description: "add run timing, HTTP details and trigger to results",
+        apply: add_run_details,
+    },
+    Migration {
+        version: 7,
+        description: "add retry policy to jobs and attempts to results",
+        apply: add_retries,
+    },
+    Migration {
+        version: 8,
+        description: "create scraper_settings table",
+        apply: create_scraper_settings,
+    },
+    Migration {
+        version: 9,
+        description: "add robots.txt setting and failure reasons",
+        apply: add_robots_compliance,
+    },
+    Migration {
+        version: 10,
+        description: "add jobs.accept_invalid_certs",
+        apply: add_accept_invalid_certs,
+    },
+    Migration {
+        version: 11,
+        description: "add jobs.request",
+        apply: add_request_spec,
+    },
+    Migration {
+        version: 12,
+        description: "add jobs.login and cookie_jars table",
+        apply: add_login_sessions,
+    },
+    Migration {
+        version: 13,
+        description: "add jobs.pagination",
+        apply: add_pagination,
+    },
+    Migration {
+        version: 14,
+        description: "add jobs.crawl",
+        apply: add_crawl,
+    },
+    Migration {
+        version: 15,
+        description: "add jobs.url_options",
+        apply: add_url_options,
+    },
+    Migration {
+        version: 16,
+        description: "add jobs.transforms",
+        apply: add_transforms,
+    },
+    Migration {
+        version: 17,
+        description: "add jobs.validation",
+        apply: add_validation,
+    },
+    Migration {
+        version: 18,
+        description: "add jobs.store_snapshots and page snapshot tables",
+        apply: add_snapshots,
+    },
+    Migration {
+        version: 19,
+        description: "add jobs.locale",
+        apply: add_locale,
+    },
+    Migration {
+        version: 20,
+        description: "drop results.scraped_data in favour of result_items",
+        apply: drop_scraped_data,
+    },
+];
+
+#[cfg(test)]
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<i32> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Applies every pending migration. When `db_path` points to an existing
+/// database with data in it, a copy is written next to it first.
+pub fn run_pending(conn: &Connection, db_path: Option<&Path>) -> Result<()> {
+    let current = current_version(conn)?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(path) = db_path {
+        if path.exists() && has_user_tables(conn)? {
+            let backup_path = backup_database(conn, path, current)?;
+            info!("Backed up database to {:?} before migrating", backup_path);
+        }
+    }
+
+    for migration in pending {
+        info!("Applying migration {}: {}", migration.version, migration.description);
+
+        let tx = conn.unchecked_transaction()?;
+        (migration.apply)(&tx)
+            .map_err(|e| anyhow!("Migration {} failed: {}", migration.version, e))?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn has_user_tables(conn: &Connection) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn backup_database(conn: &Connection, db_path: &Path, version: i32) -> Result<PathBuf> {
+    let file_name = db_path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid database path: {:?}", db_path))?;
+    let backup_path = db_path.with_file_name(format!(
+        "{}.v{}.{}.bak",
+        file_name,
+        version,
+        Utc::now().format("%Y%m%d_%H%M%S")
+    ));
+
+    conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy().to_string()])
+        .map_err(|e| anyhow!("Failed to back up database: {}", e))?;
+
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    Ok(exists)
+}
+
+// Databases created before versioning may already have some of these
+// columns, so steps that alter tables check first.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn create_initial_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            url TEXT NOT NULL,
+            selector_type TEXT NOT NULL,
+            selector TEXT NOT NULL,
+            data_type TEXT NOT NULL,
+            schedule TEXT NOT NULL,
+            user_agent TEXT,
+            proxy_url TEXT,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS results (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            scraped_data TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            success BOOLEAN NOT NULL,
+            error_message TEXT,
+            FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS email_config (
+            id INTEGER PRIMARY KEY,
+            smtp_server TEXT NOT NULL,
+            smtp_port INTEGER NOT NULL,
+            username TEXT NOT NULL,
+            password TEXT NOT NULL,
+            sender_email TEXT NOT NULL,
+            receiver_email TEXT NOT NULL,
+            use_tls BOOLEAN NOT NULL DEFAULT 1
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+fn add_job_fields(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "item_selector", "TEXT")?;
+    add_column_if_missing(conn, "jobs", "fields", "TEXT NOT NULL DEFAULT '[]'")?;
+    Ok(())
+}
+
+// One row per extracted value. Values of single-selector jobs have no field
+// name; record fields share the ordinal of the record they belong to.
+// Results saved before this step kept their values newline-joined in
+// `scraped_data`, so those are split into items. The rows are written here
+// rather than through the database module so that later changes there
+// can't alter what this step does.
+fn create_result_items(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS result_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            result_id INTEGER NOT NULL,
+            ordinal INTEGER NOT NULL,
+            field_name TEXT,
+            value TEXT NOT NULL,
+            FOREIGN KEY (result_id) REFERENCES results (id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_result_items_result_id ON result_items (result_id, ordinal);
+        "#,
+    )?;
+
+    let mut stmt = conn.prepare("SELECT id, scraped_data FROM results")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    drop(stmt);
+
+    let mut insert = conn.prepare(
+        "INSERT INTO result_items (result_id, ordinal, value) VALUES (?1, ?2, ?3)"
+    )?;
+    for (result_id, scraped_data) in rows {
+        let lines = scraped_data.split('\n').filter(|line| !line.trim().is_empty());
+        for (ordinal, value) in lines.enumerate() {
+            insert.execute(params![result_id, ordinal as i64, value])?;
+        }
+    }
+    Ok(())
+}
+
+fn add_change_detection(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "results", "content_hash", "TEXT")?;
+    add_column_if_missing(conn, "results", "changed", "BOOLEAN")?;
+    add_column_if_missing(conn, "results", "diff", "TEXT")?;
+    Ok(())
+}
+
+fn add_alert_rules(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "alert_rules", "TEXT NOT NULL DEFAULT '[]'")
+}
+
+fn add_run_details(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "results", "started_at", "TEXT")?;
+    add_column_if_missing(conn, "results", "finished_at", "TEXT")?;
+    add_column_if_missing(conn, "results", "duration_ms", "INTEGER")?;
+    add_column_if_missing(conn, "results", "http_status", "INTEGER")?;
+    add_column_if_missing(conn, "results", "bytes_fetched", "INTEGER")?;
+    add_column_if_missing(conn, "results", "run_trigger", "TEXT NOT NULL DEFAULT 'scheduled'")?;
+    Ok(())
+}
+
+fn add_retries(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "retry_policy", "TEXT")?;
+    add_column_if_missing(conn, "results", "attempts", "TEXT NOT NULL DEFAULT '[]'")?;
+    Ok(())
+}
+
+fn create_scraper_settings(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS scraper_settings (
+            id INTEGER PRIMARY KEY,
+            max_concurrent_requests INTEGER NOT NULL,
+            max_concurrent_per_host INTEGER NOT NULL,
+            min_host_delay_ms INTEGER NOT NULL
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+fn add_robots_compliance(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "scraper_settings", "respect_robots_txt", "BOOLEAN NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "results", "failure_reason", "TEXT")?;
+    Ok(())
+}
+
+fn add_accept_invalid_certs(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "accept_invalid_certs", "BOOLEAN NOT NULL DEFAULT 0")
+}
+
+fn add_request_spec(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "request", "TEXT")
+}
+
+fn add_login_sessions(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "login", "TEXT")?;
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS cookie_jars (
+            job_id INTEGER PRIMARY KEY,
+            cookies TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (job_id) REFERENCES jobs (id) ON DELETE CASCADE
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+fn add_pagination(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "pagination", "TEXT")
+}
+
+fn add_crawl(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "crawl", "TEXT")
+}
+
+fn add_url_options(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "url_options", "TEXT")
+}
+
+fn add_transforms(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "transforms", "TEXT")
+}
+
+fn add_validation(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "validation", "TEXT")
+}
+
+fn add_snapshots(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "store_snapshots", "BOOLEAN NOT NULL DEFAULT 0")?;
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS snapshot_bodies (
+            hash TEXT PRIMARY KEY,
+            body BLOB NOT NULL,
+            size INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS result_snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            result_id INTEGER NOT NULL,
+            ordinal INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            url TEXT NOT NULL,
+            status INTEGER NOT NULL,
+            headers TEXT NOT NULL,
+            body_hash TEXT NOT NULL,
+            FOREIGN KEY (result_id) REFERENCES results (id) ON DELETE CASCADE,
+            FOREIGN KEY (body_hash) REFERENCES snapshot_bodies (hash)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_result_snapshots_result_id ON result_snapshots (result_id, ordinal);
+        "#,
+    )?;
+    Ok(())
+}
+
+fn add_locale(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "jobs", "locale", "TEXT")
+}
+
+// Values have been stored in result_items since version 3; the joined copy
+// is rebuilt from them when results are loaded
+fn drop_scraped_data(conn: &Connection) -> Result<()> {
+    if column_exists(conn, "results", "scraped_data")? {
+        conn.execute("ALTER TABLE results DROP COLUMN scraped_data", [])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_versions_are_ordered() {
+        let versions: Vec<i32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<i32> = (1..=MIGRATIONS.len() as i32).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn test_fresh_database_reaches_latest_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_pending(&conn, None).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(table_exists(&conn, "result_items").unwrap());
+        assert!(column_exists(&conn, "jobs", "fields").unwrap());
+
+        // Running again is a no-op
+        run_pending(&conn, None).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_unversioned_database_is_upgraded() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_initial_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO jobs (id, name, url, selector_type, selector, data_type, schedule,
+                               created_at, updated_at)
+             VALUES (1, 'Job', 'https://example.com', 'css', 'p', 'text', '0 * * * * *',
+                     '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00')",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO results (job_id, scraped_data, timestamp, success)
+             VALUES (1, 'first' || char(10) || 'second', '2024-01-01T00:00:00+00:00', 1)",
+            [],
+        ).unwrap();
+
+        run_pending(&conn, None).unwrap();
+
+        let values: Vec<String> = conn
+            .prepare("SELECT value FROM result_items WHERE result_id = 1 ORDER BY ordinal")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        assert_eq!(values, vec!["first".to_string(), "second".to_string()]);
+        assert!(!column_exists(&conn, "results", "scraped_data").unwrap());
+    }
+
+    #[test]
+    fn test_backup_is_written_before_migrating() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("scraper.db");
+
+        let conn = Connection::open(&db_path).unwrap();
+        create_initial_tables(&conn).unwrap();
+        run_pending(&conn, Some(&db_path)).unwrap();
+
+        let backups = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("scraper.db.v0."))
+            .count();
+        assert_eq!(backups, 1);
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use crate::numbers::DEFAULT_LOCALE;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrapingJob {
@@ -12,31 +14,657 @@ pub struct ScrapingJob {
     pub schedule: String,
     pub user_agent: Option<String>,
     pub proxy_url: Option<String>,
+    /// CSS selector for the repeating container each record is extracted from.
+    /// When unset, the whole document is treated as a single item.
+    #[serde(default)]
+    pub item_selector: Option<String>,
+    /// Named fields extracted relative to each item. When empty the job falls
+    /// back to the single `selector`/`data_type` pair.
+    #[serde(default)]
+    pub fields: Vec<FieldSpec>,
+    /// Conditions that send an email notification after a run.
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRule>,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// Skip TLS certificate validation, for internal sites with self-signed
+    /// certificates.
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    /// Method, headers, cookies, query parameters and body of the request.
+    #[serde(default)]
+    pub request: RequestSpec,
+    /// Login performed before scraping when the job has no live session.
+    #[serde(default)]
+    pub login: Option<LoginSpec>,
+    /// Follow further pages and merge their items into the same run.
+    #[serde(default)]
+    pub pagination: Option<PaginationSpec>,
+    /// Treat the job URL as an index page and follow its links to detail
+    /// pages. The job's selector or fields are then applied to each detail
+    /// page instead of the index page.
+    #[serde(default)]
+    pub crawl: Option<CrawlSpec>,
+    #[serde(default)]
+    pub url_options: UrlOptions,
+    /// Applied to the extracted values before they are saved. For jobs with
+    /// fields they run on every field, before the field's own transforms.
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    /// Expectations checked after the transforms. A run that breaks them is
+    /// stored as failed with `FailureReason::ValidationFailed`.
+    #[serde(default)]
+    pub validation: ValidationRules,
+    /// Locale the page writes numbers in, such as `de-DE`. Alert thresholds
+    /// and validation ranges read numbers with it; `en-US` when unset.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Keep the fetched pages of each run so they can be extracted again
+    /// after the selectors change.
+    #[serde(default)]
+    pub store_snapshots: bool,
     pub is_active: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+impl Default for ScrapingJob {
+    fn default() -> Self {
+        ScrapingJob {
+            id: None,
+            name: String::new(),
+            url: String::new(),
+            selector_type: SelectorType::CSS,
+            selector: String::new(),
+            data_type: DataType::Text,
+            schedule: "daily".to_string(),
+            user_agent: None,
+            proxy_url: None,
+            item_selector: None,
+            fields: Vec::new(),
+            alert_rules: Vec::new(),
+            retry_policy: RetryPolicy::default(),
+            accept_invalid_certs: false,
+            request: RequestSpec::default(),
+            login: None,
+            pagination: None,
+            crawl: None,
+            url_options: UrlOptions::default(),
+            transforms: Vec::new(),
+            validation: ValidationRules::default(),
+            locale: None,
+            store_snapshots: false,
+            is_active: true,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    pub selector_type: SelectorType,
+    pub selector: String,
+    pub data_type: DataType,
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+}
+
+/// A post-processing step applied to extracted values, in the order the
+/// steps are listed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Transform {
+    Trim,
+    Lowercase,
+    /// `replacement` may refer to capture groups as `$1` or `${name}`.
+    RegexReplace { pattern: String, replacement: String },
+    /// Characters from `start`, up to `length` of them.
+    Substring { start: usize, length: Option<usize> },
+    /// Parses a number written for `locale` (e.g. `de-DE` for "1.234,5"),
+    /// ignoring units and other text around it.
+    ParseNumber { locale: String },
+    /// Like `ParseNumber`, formatted with two decimals.
+    ParseCurrency { locale: String },
+    /// Parses a date with a chrono format string such as `%d/%m/%Y` and
+    /// rewrites it in ISO 8601.
+    ParseDate { format: String },
+    /// Splits every value into several.
+    Split { separator: String },
+    /// Joins all values into one.
+    Join { separator: String },
+    DefaultIfEmpty { value: String },
+    Dedupe,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestSpec {
+    pub method: HttpMethod,
+    pub headers: BTreeMap<String, String>,
+    /// Sent as a single `Cookie` header.
+    pub cookies: BTreeMap<String, String>,
+    /// Appended to the job URL's own query string.
+    pub query: BTreeMap<String, String>,
+    pub body: Option<RequestBody>,
+}
+
+/// Stored and exported in place of secret header and cookie values; the
+/// values themselves are kept in the system credential store.
+pub const SECRET_PLACEHOLDER: &str = "********";
+
+/// Header and cookie values taken out of a job's request spec.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestSecrets {
+    pub headers: BTreeMap<String, String>,
+    pub cookies: BTreeMap<String, String>,
+}
+
+impl RequestSecrets {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.cookies.is_empty()
+    }
+}
+
+impl RequestSpec {
+    /// Headers that usually carry credentials, such as `Authorization` or
+    /// `X-Api-Key`. Every cookie is treated as a secret.
+    pub fn is_secret_header(name: &str) -> bool {
+        const SECRET_PARTS: &[&str] = &["auth", "token", "secret", "api-key", "apikey", "password", "session"];
+        let name = name.to_ascii_lowercase();
+        name == "cookie" || SECRET_PARTS.iter().any(|part| name.contains(part))
+    }
+
+    /// Replaces secret header values and all cookie values with the
+    /// placeholder and returns them. Values that are already the
+    /// placeholder were left unchanged by the user and are taken from
+    /// `stored`.
+    pub fn take_secrets(&mut self, stored: &RequestSecrets) -> RequestSecrets {
+        let mut secrets = RequestSecrets::default();
+        for (name, value) in self.headers.iter_mut().filter(|(name, _)| Self::is_secret_header(name)) {
+            let secret = match value.as_str() {
+                SECRET_PLACEHOLDER => stored.headers.get(name).cloned(),
+                _ => Some(std::mem::replace(value, SECRET_PLACEHOLDER.to_string())),
+            };
+            secrets.headers.extend(secret.map(|secret| (name.clone(), secret)));
+        }
+        for (name, value) in self.cookies.iter_mut() {
+            let secret = match value.as_str() {
+                SECRET_PLACEHOLDER => stored.cookies.get(name).cloned(),
+                _ => Some(std::mem::replace(value, SECRET_PLACEHOLDER.to_string())),
+            };
+            secrets.cookies.extend(secret.map(|secret| (name.clone(), secret)));
+        }
+        secrets
+    }
+
+    /// Puts stored values back in place of the placeholders.
+    pub fn restore_secrets(&mut self, secrets: &RequestSecrets) {
+        for (name, value) in self.headers.iter_mut().filter(|(_, value)| *value == SECRET_PLACEHOLDER) {
+            if let Some(secret) = secrets.headers.get(name) {
+                value.clone_from(secret);
+            }
+        }
+        for (name, value) in self.cookies.iter_mut().filter(|(_, value)| *value == SECRET_PLACEHOLDER) {
+            if let Some(secret) = secrets.cookies.get(name) {
+                value.clone_from(secret);
+            }
+        }
+    }
+
+    pub fn has_placeholders(&self) -> bool {
+        self.headers.values().chain(self.cookies.values()).any(|value| value == SECRET_PLACEHOLDER)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RequestBody {
+    /// URL-encoded form fields.
+    Form(BTreeMap<String, String>),
+    Json(serde_json::Value),
+    Raw { content_type: Option<String>, content: String },
+}
+
+/// A form login. The password is kept in the system credential store, not
+/// in the job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoginSpec {
+    pub url: String,
+    pub username: String,
+    pub username_field: String,
+    pub password_field: String,
+    /// Other form fields sent with the credentials.
+    pub extra_fields: BTreeMap<String, String>,
+    /// Text that only appears on pages served to logged-out visitors. Used
+    /// together with 401/403 responses and redirects to `url` to detect an
+    /// expired session.
+    pub logged_out_marker: Option<String>,
+}
+
+impl Default for LoginSpec {
+    fn default() -> Self {
+        LoginSpec {
+            url: String::new(),
+            username: String::new(),
+            username_field: "username".to_string(),
+            password_field: "password".to_string(),
+            extra_fields: BTreeMap::new(),
+            logged_out_marker: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaginationSpec {
+    pub mode: Pagination,
+    /// Total pages fetched per run, including the first one.
+    pub max_pages: u32,
+    /// Stop at the first page whose items were all seen on earlier pages.
+    /// Pages without any items always stop pagination.
+    pub stop_on_no_new_items: bool,
+}
+
+impl Default for PaginationSpec {
+    fn default() -> Self {
+        PaginationSpec {
+            mode: Pagination::PageParam { param: "page".to_string(), start: 1 },
+            max_pages: 10,
+            stop_on_no_new_items: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Pagination {
+    /// Follow the `href` of the first element matching a CSS selector; stops
+    /// when no such link is found.
+    NextLink { selector: String },
+    /// Set a page number query parameter, starting at `start`.
+    PageParam { param: String, start: u64 },
+    /// Set an offset query parameter, starting at `start` and growing by
+    /// `step` per page.
+    Offset { param: String, start: u64, step: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlSpec {
+    /// CSS selector for the links on the index page.
+    pub link_selector: String,
+    /// Attribute holding the link target.
+    pub link_attribute: String,
+    /// Detail pages fetched per run, after removing duplicate links.
+    pub max_links: u32,
+    /// When set, records get an extra field with this name holding the
+    /// detail page URL.
+    pub url_field: Option<String>,
+}
+
+impl Default for CrawlSpec {
+    fn default() -> Self {
+        CrawlSpec {
+            link_selector: String::new(),
+            link_attribute: "href".to_string(),
+            max_links: 50,
+            url_field: None,
+        }
+    }
+}
+
+/// Post-processing of URLs extracted from URL attributes such as `href` and
+/// `src`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrlOptions {
+    /// Resolve relative URLs against the final page URL, or the page's
+    /// `<base href>` when it has one.
+    pub resolve: bool,
+    /// Drop repeated URLs. Jobs with fields drop records whose URL fields
+    /// all repeat an earlier record's.
+    pub dedupe: bool,
+    /// Drop URLs pointing to another host, and values that can't be
+    /// resolved into a URL at all. Single-selector jobs lose the value; for
+    /// jobs with fields the value is left empty.
+    pub same_host_only: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationRules {
+    pub min_items: Option<usize>,
+    pub max_items: Option<usize>,
+    /// Regex every value of a single-selector job must match.
+    pub item_pattern: Option<String>,
+    /// Range every value of a single-selector job must be a number in.
+    pub value_range: Option<NumericRange>,
+    /// Fields that must be non-empty in every record.
+    pub required_fields: Vec<String>,
+    /// Regex per field that every record's value must match.
+    pub field_patterns: BTreeMap<String, String>,
+    /// Range per field that every record's value must be a number in.
+    pub field_ranges: BTreeMap<String, NumericRange>,
+}
+
+/// Inclusive bounds; a missing bound is unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NumericRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// A document to try selectors on without fetching anything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PlaygroundSource {
+    Html(String),
+    /// A page stored with a run; `index` counts from 0 in fetch order.
+    Snapshot { result_id: i64, index: usize },
+    /// A file in the export directory, such as an exported page.
+    File(String),
+}
+
+/// The page selector suggestions look for the example value in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SuggestionSource {
+    /// The job's page, fetched now with the job's request settings, session
+    /// cookies and the politeness limits.
+    Job(i64),
+    /// A page that is already at hand.
+    Document(PlaygroundSource),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaygroundRequest {
+    pub source: PlaygroundSource,
+    pub selector_type: SelectorType,
+    pub selector: String,
+    pub data_type: DataType,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlaygroundResult {
+    pub count: usize,
+    pub matches: Vec<SelectorMatch>,
+}
+
+/// One match of a selector and where it was found: a CSS path, an XPath
+/// location path, a normalized JSONPath, a metadata key or, for regexes,
+/// the byte offset of the match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectorMatch {
+    pub path: String,
+    pub value: String,
+}
+
+/// A CSS selector proposed for an example value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectorSuggestion {
+    pub selector: String,
+    pub basis: SelectorBasis,
+    pub match_count: usize,
+    /// The text of the first matches.
+    pub matches: Vec<String>,
+}
+
+/// What a suggested selector relies on, from the most to the least likely
+/// to survive a redesign.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SelectorBasis {
+    Id,
+    /// Attributes such as `itemprop` or `data-testid`.
+    Attribute,
+    /// A class inside an element with an id.
+    IdAncestor,
+    Class,
+    /// A tag inside a parent with a class.
+    ParentClass,
+    /// The element's position in the document.
+    Structure,
+}
+
+/// What a test scrape found, before and after the job's transforms.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestScrapePreview {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+/// A single structured item, keyed by field name.
+pub type ScrapedRecord = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScrapedData {
+    /// Values produced by a single-selector job.
+    pub values: Vec<String>,
+    /// Records produced by a job with named fields.
+    pub records: Vec<ScrapedRecord>,
+    pub http_status: Option<u16>,
+    pub bytes_fetched: u64,
+    /// The pages the items came from, kept for jobs with `store_snapshots`.
+    #[serde(skip)]
+    pub snapshots: Vec<PageSnapshot>,
+}
+
+/// A fetched page stored with a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageSnapshot {
+    pub kind: SnapshotKind,
+    /// The URL after redirects.
+    pub url: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SnapshotKind {
+    /// The job URL or one of its pagination pages.
+    Page,
+    /// A detail page reached by crawling.
+    Detail,
+}
+
+/// Outcome of re-extracting a job's stored snapshots.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackfillSummary {
+    pub results_updated: usize,
+    /// Runs whose snapshots no longer produce valid data.
+    pub results_failed: usize,
+}
+
+impl ScrapedData {
+    pub fn len(&self) -> usize {
+        if self.records.is_empty() {
+            self.values.len()
+        } else {
+            self.records.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// One value per item; records are rendered as compact JSON.
+    pub fn item_values(&self) -> Vec<String> {
+        item_values(&self.values, &self.records)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts per run, including the first one.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub retry_on_status: Vec<u16>,
+    pub retry_on_errors: Vec<NetworkErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay_ms: 5_000,
+            max_delay_ms: 300_000,
+            retry_on_status: vec![429, 500, 502, 503, 504],
+            retry_on_errors: vec![NetworkErrorKind::Timeout, NetworkErrorKind::Connect],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NetworkErrorKind {
+    Timeout,
+    Connect,
+    Other,
+}
+
+/// One try within a run; a run records all of its attempts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunAttempt {
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    pub http_status: Option<u16>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AlertRule {
+    /// The extracted content differs from the previous successful run.
+    OnChange,
+    /// The run failed.
+    OnFailure,
+    /// An item (or the given field of a record) matches the regex.
+    ItemMatches { pattern: String, field: Option<String> },
+    /// A numeric item (or the given field of a record) is below the threshold.
+    ValueBelow { threshold: f64, field: Option<String> },
+    /// A successful run extracted nothing.
+    NoItems,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertTrigger {
+    pub rule: AlertRule,
+    pub reason: String,
+    pub values: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SelectorType {
     CSS,
     Regex,
+    XPath,
+    /// Evaluated against the response parsed as JSON.
+    JsonPath,
+    /// A key into the page's JSON-LD, microdata and `<meta>` tags, such as
+    /// `Product.offers.price` or `og:title`.
+    Metadata,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DataType {
+    /// All descendant text, joined with spaces.
     Text,
     Attribute(String),
+    InnerHtml,
+    OuterHtml,
+    /// Only the element's direct text nodes, without its children's text.
+    OwnText,
+    /// Descendant text without `<script>`/`<style>` content, with runs of
+    /// whitespace collapsed to single spaces.
+    CleanText,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ScrapingResult {
     pub id: Option<i64>,
     pub job_id: i64,
+    /// The items joined by newlines, for display. Derived from `items` and
+    /// `records` and not stored.
     pub scraped_data: String,
+    /// Individual values of a single-selector job, in extraction order.
+    #[serde(default)]
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub records: Vec<ScrapedRecord>,
     pub timestamp: DateTime<Utc>,
     pub success: bool,
     pub error_message: Option<String>,
+    /// SHA-256 of the extracted items, set for successful runs.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Whether the content differs from the previous successful run of the
+    /// same job. `None` for failed runs and for the first successful run.
+    #[serde(default)]
+    pub changed: Option<bool>,
+    #[serde(default)]
+    pub diff: Option<ResultDiff>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    #[serde(default)]
+    pub http_status: Option<u16>,
+    #[serde(default)]
+    pub bytes_fetched: Option<i64>,
+    #[serde(default)]
+    pub trigger: RunTrigger,
+    #[serde(default)]
+    pub attempts: Vec<RunAttempt>,
+    /// Why a failed run failed; `None` for successful runs.
+    #[serde(default)]
+    pub failure_reason: Option<FailureReason>,
+    /// Pages to store with the run; only set when saving a new result.
+    #[serde(skip)]
+    pub snapshots: Vec<PageSnapshot>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RunTrigger {
+    #[default]
+    Scheduled,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FailureReason {
+    Network,
+    Http,
+    /// robots.txt disallows the URL. An unreachable robots.txt is a
+    /// network or HTTP failure instead.
+    RobotsBlocked,
+    /// The login step failed or the session could not be restored.
+    LoginFailed,
+    /// The page was scraped but the data broke the job's validation rules.
+    ValidationFailed,
+    Other,
+}
+
+/// Item-level comparison against the previous successful run. Records are
+/// compared by their compact JSON form.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResultDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,12 +678,39 @@ pub struct EmailConfig {
     pub use_tls: bool,
 }
 
+/// Limits shared by all jobs to avoid overloading target sites.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScraperSettings {
+    pub max_concurrent_requests: u32,
+    pub max_concurrent_per_host: u32,
+    /// Minimum time between the starts of two requests to the same host.
+    pub min_host_delay_ms: u32,
+    /// Fetch robots.txt before each run and refuse disallowed URLs. A
+    /// `Crawl-delay` longer than `min_host_delay_ms` takes precedence.
+    pub respect_robots_txt: bool,
+}
+
+impl Default for ScraperSettings {
+    fn default() -> Self {
+        ScraperSettings {
+            max_concurrent_requests: 4,
+            max_concurrent_per_host: 1,
+            min_host_delay_ms: 1_000,
+            respect_robots_txt: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportRequest {
     pub job_id: i64,
     pub format: ExportFormat,
     pub start_date: Option<DateTime<Utc>>,
     pub end_date: Option<DateTime<Utc>>,
+    /// Only export runs that changed, with their added and removed items.
+    #[serde(default)]
+    pub changes_only: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +732,48 @@ pub struct JobStats {
     pub active_jobs: i64,
     pub total_results: i64,
     pub last_run: Option<DateTime<Utc>>,
+    /// Active jobs whose recent runs suggest broken selectors.
+    #[serde(default)]
+    pub degraded_jobs: i64,
+    #[serde(default)]
+    pub degraded_job_ids: Vec<i64>,
+}
+
+/// The parts of a run that health scoring looks at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub timestamp: DateTime<Utc>,
+    pub success: bool,
+    pub failure_reason: Option<FailureReason>,
+    /// Values or records stored for the run.
+    pub item_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HealthStatus {
+    Healthy,
+    /// Item counts dropped sharply or several runs in a row found nothing.
+    Degraded,
+    /// The job has not run yet.
+    Unknown,
+}
+
+/// How well a job's recent runs are doing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobHealth {
+    pub job_id: i64,
+    pub status: HealthStatus,
+    /// 0 to 100, weighing the success rate and the latest item count against
+    /// the baseline equally. `None` until the job has run.
+    pub score: Option<u8>,
+    pub runs_considered: usize,
+    pub success_rate: Option<f64>,
+    pub latest_items: Option<usize>,
+    /// Median item count of the earlier runs that fetched the page.
+    pub baseline_items: Option<f64>,
+    pub consecutive_empty: u32,
+    /// Why the job is degraded; empty otherwise.
+    pub reasons: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,11 +785,63 @@ pub struct ExportFileInfo {
     pub file_type: String,
 }
 
+impl ScrapingJob {
+    pub fn has_fields(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    /// The locale numbers on the job's pages are read with.
+    pub fn number_locale(&self) -> &str {
+        self.locale.as_deref().unwrap_or(DEFAULT_LOCALE)
+    }
+
+    /// A copy that is safe to write to export files: secret headers,
+    /// cookies and the login username are replaced with the placeholder.
+    pub fn redacted(&self) -> ScrapingJob {
+        let mut job = self.clone();
+        job.request.take_secrets(&RequestSecrets::default());
+        if let Some(login) = &mut job.login {
+            login.username = SECRET_PLACEHOLDER.to_string();
+        }
+        job
+    }
+
+    pub fn field_names(&self) -> Vec<String> {
+        let url_field = self.crawl.as_ref().and_then(|crawl| crawl.url_field.clone());
+        url_field
+            .into_iter()
+            .chain(self.fields.iter().map(|field| field.name.clone()))
+            .collect()
+    }
+}
+
+impl ScrapingResult {
+    /// One value per item; records are rendered as compact JSON.
+    pub fn item_values(&self) -> Vec<String> {
+        item_values(&self.items, &self.records)
+    }
+}
+
+// Shared by scraped data and stored results so both render records alike
+fn item_values(values: &[String], records: &[ScrapedRecord]) -> Vec<String> {
+    if records.is_empty() {
+        values.to_vec()
+    } else {
+        records
+            .iter()
+            .map(|record| serde_json::to_string(record).unwrap_or_default())
+            .collect()
+    }
+}
+
 impl std::fmt::Display for SelectorType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SelectorType::CSS => write!(f, "css"),
             SelectorType::Regex => write!(f, "regex"),
+            SelectorType::XPath => write!(f, "xpath"),
+            SelectorType::JsonPath => write!(f, "jsonpath"),
+            SelectorType::Metadata => write!(f, "metadata"),
         }
     }
 }
@@ -104,16 +853,94 @@ impl std::str::FromStr for SelectorType {
         match s.to_lowercase().as_str() {
             "css" => Ok(SelectorType::CSS),
             "regex" => Ok(SelectorType::Regex),
+            "xpath" => Ok(SelectorType::XPath),
+            "jsonpath" => Ok(SelectorType::JsonPath),
+            "metadata" => Ok(SelectorType::Metadata),
             _ => Err(anyhow::anyhow!("Invalid selector type: {}", s)),
         }
     }
 }
 
+impl std::fmt::Display for RunTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunTrigger::Scheduled => write!(f, "scheduled"),
+            RunTrigger::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+impl std::str::FromStr for RunTrigger {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "scheduled" => Ok(RunTrigger::Scheduled),
+            "manual" => Ok(RunTrigger::Manual),
+            _ => Err(anyhow::anyhow!("Invalid run trigger: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FailureReason::Network => write!(f, "network"),
+            FailureReason::Http => write!(f, "http"),
+            FailureReason::RobotsBlocked => write!(f, "robots_blocked"),
+            FailureReason::LoginFailed => write!(f, "login_failed"),
+            FailureReason::ValidationFailed => write!(f, "validation_failed"),
+            FailureReason::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl std::fmt::Display for SnapshotKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotKind::Page => write!(f, "page"),
+            SnapshotKind::Detail => write!(f, "detail"),
+        }
+    }
+}
+
+impl std::str::FromStr for SnapshotKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "page" => Ok(SnapshotKind::Page),
+            "detail" => Ok(SnapshotKind::Detail),
+            _ => Err(anyhow::anyhow!("Invalid snapshot kind: {}", s)),
+        }
+    }
+}
+
+impl std::str::FromStr for FailureReason {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "network" => Ok(FailureReason::Network),
+            "http" => Ok(FailureReason::Http),
+            "robots_blocked" => Ok(FailureReason::RobotsBlocked),
+            "login_failed" => Ok(FailureReason::LoginFailed),
+            "validation_failed" => Ok(FailureReason::ValidationFailed),
+            "other" => Ok(FailureReason::Other),
+            _ => Err(anyhow::anyhow!("Invalid failure reason: {}", s)),
+        }
+    }
+}
+
 impl std::fmt::Display for DataType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DataType::Text => write!(f, "text"),
             DataType::Attribute(attr) => write!(f, "attribute:{}", attr),
+            DataType::InnerHtml => write!(f, "inner_html"),
+            DataType::OuterHtml => write!(f, "outer_html"),
+            DataType::OwnText => write!(f, "own_text"),
+            DataType::CleanText => write!(f, "clean_text"),
         }
     }
 }
@@ -122,12 +949,16 @@ impl std::str::FromStr for DataType {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "text" {
-            Ok(DataType::Text)
-        } else if let Some(attr) = s.strip_prefix("attribute:") {
-            Ok(DataType::Attribute(attr.to_string()))
-        } else {
-            Err(anyhow::anyhow!("Invalid data type: {}", s))
+        if let Some(attr) = s.strip_prefix("attribute:") {
+            return Ok(DataType::Attribute(attr.to_string()));
+        }
+        match s {
+            "text" => Ok(DataType::Text),
+            "inner_html" => Ok(DataType::InnerHtml),
+            "outer_html" => Ok(DataType::OuterHtml),
+            "own_text" => Ok(DataType::OwnText),
+            "clean_text" => Ok(DataType::CleanText),
+            _ => Err(anyhow::anyhow!("Invalid data type: {}", s)),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+// Scraped numbers come with currency symbols, units and grouping separators
+// attached. Alerts, validation rules and transforms all read them with the
+// one parser here, so a value that passes one of them passes the others.
+
+/// The locale used where a job doesn't name one.
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// Parses the first number in a value such as "$1,299.99", "-4 %" or, for
+/// `de-DE`, "1.234,5 kg". `locale` decides whether a comma or a point is
+/// the decimal separator; the other one and apostrophes are taken as
+/// grouping separators, and so are spaces where the locale groups with
+/// them, as in French "1 299,90".
+pub fn parse_number(value: &str, locale: &str) -> Option<f64> {
+    let decimal = decimal_separator(locale);
+    let space_grouping = groups_with_spaces(locale);
+    let start = value.find(|c: char| c.is_ascii_digit())?;
+    let prefix = &value[..start];
+    let rest = &value[start..];
+
+    let mut number = String::new();
+    if prefix.ends_with(decimal) {
+        number.push_str("0.");
+    }
+    let mut end = rest.len();
+    let mut chars = rest.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let before_digit = chars.peek().is_some_and(|(_, next)| next.is_ascii_digit());
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else if c == decimal && before_digit && !number.contains('.') {
+            number.push('.');
+        } else if is_group_separator(c, decimal, space_grouping) && before_digit && !number.contains('.') {
+            continue;
+        } else {
+            end = index;
+            break;
+        }
+    }
+
+    let number: f64 = number.parse().ok()?;
+    let negative = is_negative(prefix, &rest[end..], decimal);
+    Some(if negative { -number } else { number })
+}
+
+// A sign counts only where it touches the number, possibly through a
+// currency symbol as in "-$5" or "$-5". A hyphen inside a word, as in
+// "SKU-4412", or one further back, as in "Free-shipping $5", is not a sign.
+// Accountants' parentheses count only when they close right after the
+// number, as in "(5.00)" or "(5,00 €)", but not in "(5 items)".
+fn is_negative(prefix: &str, suffix: &str, decimal: char) -> bool {
+    let prefix = prefix.strip_suffix(decimal).unwrap_or(prefix);
+    let mut before = prefix.trim_end_matches(is_currency_symbol).chars().rev();
+    match before.next() {
+        Some('-' | '\u{2212}') => !before.next().is_some_and(char::is_alphanumeric),
+        Some('(') => suffix
+            .trim_start_matches(|c: char| c.is_whitespace() || is_currency_symbol(c))
+            .starts_with(')'),
+        _ => false,
+    }
+}
+
+fn is_currency_symbol(c: char) -> bool {
+    matches!(c, '$' | '€' | '£' | '¥' | '¢' | '₹' | '₽' | '₩' | '₺' | '₪' | '₫' | '₱' | '₴' | '₦')
+}
+
+// Languages that write "1.234,5" rather than "1,234.5"
+const DECIMAL_COMMA_LANGUAGES: &[&str] = &[
+    "bg", "cs", "da", "de", "el", "es", "fi", "fr", "hr", "hu", "id", "it", "lt", "lv",
+    "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk", "vi",
+];
+
+// Languages that group digits with a (usually non-breaking) space, as in
+// "1 234,5"
+const SPACE_GROUPING_LANGUAGES: &[&str] = &[
+    "bg", "cs", "et", "fi", "fr", "hu", "lt", "lv", "nb", "nn", "no", "pl", "ru", "sk", "sv", "uk",
+];
+
+fn language(locale: &str) -> String {
+    locale.split(['-', '_']).next().unwrap_or_default().to_lowercase()
+}
+
+fn groups_with_spaces(locale: &str) -> bool {
+    SPACE_GROUPING_LANGUAGES.contains(&language(locale).as_str())
+}
+
+fn decimal_separator(locale: &str) -> char {
+    let language = language(locale);
+    // Swiss German uses a point, unlike the rest of German
+    if locale.eq_ignore_ascii_case("de-CH") {
+        return '.';
+    }
+    if DECIMAL_COMMA_LANGUAGES.contains(&language.as_str()) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+fn is_group_separator(c: char, decimal: char, space_grouping: bool) -> bool {
+    match c {
+        ',' | '.' | '\'' => c != decimal,
+        ' ' | '\u{a0}' | '\u{202f}' => space_grouping,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("$1,299.99", DEFAULT_LOCALE), Some(1299.99));
+        assert_eq!(parse_number("Price: 42 USD", DEFAULT_LOCALE), Some(42.0));
+        assert_eq!(parse_number("-4 %", DEFAULT_LOCALE), Some(-4.0));
+        assert_eq!(parse_number("10-20 items", DEFAULT_LOCALE), Some(10.0));
+        assert_eq!(parse_number("sold out", DEFAULT_LOCALE), None);
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(parse_number("-$5", DEFAULT_LOCALE), Some(-5.0));
+        assert_eq!(parse_number("$-5", DEFAULT_LOCALE), Some(-5.0));
+        assert_eq!(parse_number("Change: \u{2212}2.5%", DEFAULT_LOCALE), Some(-2.5));
+        assert_eq!(parse_number("SKU-4412", DEFAULT_LOCALE), Some(4412.0));
+        assert_eq!(parse_number("Free-shipping $5", DEFAULT_LOCALE), Some(5.0));
+        assert_eq!(parse_number("($5.00)", DEFAULT_LOCALE), Some(-5.0));
+        assert_eq!(parse_number("(5 items)", DEFAULT_LOCALE), Some(5.0));
+    }
+
+    #[test]
+    fn test_locale_separators() {
+        assert_eq!(parse_number("1.234,5 kg", "de-DE"), Some(1234.5));
+        assert_eq!(parse_number("1'234.50", "de-CH"), Some(1234.5));
+        assert_eq!(parse_number("1\u{a0}299,90 €", "fr-FR"), Some(1299.9));
+        assert_eq!(parse_number("(5,00 €)", "fr-FR"), Some(-5.0));
+        assert_eq!(parse_number("€,50", "fr-FR"), Some(0.5));
+        assert_eq!(parse_number("10 20", "fr-FR"), Some(1020.0));
+        assert_eq!(parse_number("10 20", DEFAULT_LOCALE), Some(10.0));
+        assert_eq!(parse_number("1.234 kg", "de-DE"), Some(1234.0));
+    }
+}
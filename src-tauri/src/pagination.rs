@@ -0,0 +1,98 @@
+use reqwest::Url;
+use scraper::{Html, Selector};
+use crate::models::Pagination;
+use anyhow::{Result, anyhow};
+
+/// The URL of page `index` (0 for the first page) of a paginated job.
+/// `current_url` and `current_html` describe the page fetched last and are
+/// only used to find a "next" link. `None` means there is no further page.
+pub fn page_url(pagination: &Pagination, job_url: &str, index: u32, current_url: &str, current_html: &str) -> Result<Option<String>> {
+    match pagination {
+        Pagination::NextLink { selector } => {
+            if index == 0 {
+                return Ok(Some(job_url.to_string()));
+            }
+            next_link(current_html, current_url, selector)
+        }
+        Pagination::PageParam { param, start } => {
+            with_query_param(job_url, param, start.saturating_add(index as u64)).map(Some)
+        }
+        Pagination::Offset { param, start, step } => {
+            let offset = start.saturating_add((index as u64).saturating_mul(*step));
+            with_query_param(job_url, param, offset).map(Some)
+        }
+    }
+}
+
+fn next_link(html: &str, page_url: &str, selector_str: &str) -> Result<Option<String>> {
+    let selector = Selector::parse(selector_str)
+        .map_err(|e| anyhow!("Invalid next link selector '{}': {:?}", selector_str, e))?;
+    let base = Url::parse(page_url)
+        .map_err(|e| anyhow!("Invalid URL '{}': {}", page_url, e))?;
+
+    let document = Html::parse_document(html);
+    let href = document
+        .select(&selector)
+        .filter_map(|element| element.value().attr("href"))
+        .map(str::trim)
+        .find(|href| !href.is_empty() && !href.starts_with('#') && !href.starts_with("javascript:"));
+
+    Ok(href.and_then(|href| base.join(href).ok()).map(|url| url.to_string()))
+}
+
+/// Sets `param` to `value`, replacing any existing value and keeping the
+/// other query parameters.
+fn with_query_param(url: &str, param: &str, value: u64) -> Result<String> {
+    let mut url = Url::parse(url)
+        .map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+    let pairs: Vec<(String, String)> = url.query_pairs()
+        .filter(|(name, _)| name != param)
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(param, &value.to_string());
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_param_modes() {
+        let by_page = Pagination::PageParam { param: "page".to_string(), start: 1 };
+        assert_eq!(
+            page_url(&by_page, "https://example.com/search?q=x&page=7", 0, "", "").unwrap().as_deref(),
+            Some("https://example.com/search?q=x&page=1")
+        );
+        assert_eq!(
+            page_url(&by_page, "https://example.com/search?q=x", 2, "", "").unwrap().as_deref(),
+            Some("https://example.com/search?q=x&page=3")
+        );
+
+        let by_offset = Pagination::Offset { param: "offset".to_string(), start: 0, step: 25 };
+        assert_eq!(
+            page_url(&by_offset, "https://example.com/list", 3, "", "").unwrap().as_deref(),
+            Some("https://example.com/list?offset=75")
+        );
+    }
+
+    #[test]
+    fn test_next_link() {
+        let next = Pagination::NextLink { selector: "a.next".to_string() };
+        let html = r##"<a class="next" href="#">top</a><a class="next" href="?page=2">Next</a>"##;
+
+        assert_eq!(
+            page_url(&next, "https://example.com/list", 0, "", "").unwrap().as_deref(),
+            Some("https://example.com/list")
+        );
+        assert_eq!(
+            page_url(&next, "https://example.com/list", 1, "https://example.com/list?page=1", html).unwrap().as_deref(),
+            Some("https://example.com/list?page=2")
+        );
+        assert_eq!(page_url(&next, "https://example.com/list", 1, "https://example.com/list", "<p>end</p>").unwrap(), None);
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use crate::models::ScraperSettings;
+use anyhow::{Result, anyhow};
+use log::info;
+
+/// Coordinates requests from every job so that the same site is not hit by
+/// many jobs at once: a global concurrency cap, a per-host concurrency cap
+/// and a minimum delay between the starts of two requests to one host.
+pub struct Politeness {
+    state: Mutex<PolitenessState>,
+}
+
+struct PolitenessState {
+    settings: ScraperSettings,
+    global: Arc<Semaphore>,
+    hosts: HashMap<String, HostState>,
+}
+
+struct HostState {
+    semaphore: Arc<Semaphore>,
+    next_allowed: Instant,
+}
+
+/// Held for the duration of a request; dropping it frees the slots.
+pub struct PolitenessPermit {
+    _host: OwnedSemaphorePermit,
+    _global: OwnedSemaphorePermit,
+}
+
+impl Politeness {
+    pub fn new(settings: ScraperSettings) -> Self {
+        Politeness {
+            state: Mutex::new(PolitenessState {
+                global: Arc::new(Semaphore::new(settings.max_concurrent_requests.max(1) as usize)),
+                settings,
+                hosts: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn settings(&self) -> ScraperSettings {
+        self.state.lock().unwrap().settings.clone()
+    }
+
+    /// Applies new limits. Requests already in flight keep their old slots.
+    pub fn update_settings(&self, settings: ScraperSettings) {
+        let mut state = self.state.lock().unwrap();
+        state.global = Arc::new(Semaphore::new(settings.max_concurrent_requests.max(1) as usize));
+        for host in state.hosts.values_mut() {
+            host.semaphore = Arc::new(Semaphore::new(settings.max_concurrent_per_host.max(1) as usize));
+        }
+        state.settings = settings;
+        info!("Updated scraper politeness settings: {:?}", state.settings);
+    }
+
+    /// Waits until a request to `url` is allowed to start. A site's own
+    /// crawl delay is honoured when it is longer than the configured one.
+    pub async fn acquire(&self, url: &str, crawl_delay: Option<Duration>) -> Result<PolitenessPermit> {
+        let host = host_key(url)?;
+
+        let host_semaphore = {
+            let mut state = self.state.lock().unwrap();
+            Arc::clone(&state.host(&host).semaphore)
+        };
+        let host_permit = host_semaphore.acquire_owned().await
+            .map_err(|e| anyhow!("Failed to acquire host slot: {}", e))?;
+
+        // Reserve the next start time for this host before sleeping so that
+        // concurrent waiters are spaced out rather than released together
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let min_delay = Duration::from_millis(state.settings.min_host_delay_ms as u64)
+                .max(crawl_delay.unwrap_or_default());
+            let host_state = state.host(&host);
+            let now = Instant::now();
+            let start = host_state.next_allowed.max(now);
+            host_state.next_allowed = start + min_delay;
+            start - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        let global = Arc::clone(&self.state.lock().unwrap().global);
+        let global_permit = global.acquire_owned().await
+            .map_err(|e| anyhow!("Failed to acquire request slot: {}", e))?;
+
+        Ok(PolitenessPermit { _host: host_permit, _global: global_permit })
+    }
+}
+
+impl PolitenessState {
+    fn host(&mut self, host: &str) -> &mut HostState {
+        let per_host = self.settings.max_concurrent_per_host.max(1) as usize;
+        self.hosts.entry(host.to_string()).or_insert_with(|| HostState {
+            semaphore: Arc::new(Semaphore::new(per_host)),
+            next_allowed: Instant::now(),
+        })
+    }
+}
+
+impl Default for Politeness {
+    fn default() -> Self {
+        Self::new(ScraperSettings::default())
+    }
+}
+
+pub fn validate_settings(settings: &ScraperSettings) -> Result<()> {
+    if settings.max_concurrent_requests == 0 {
+        return Err(anyhow!("Maximum concurrent requests must be at least 1"));
+    }
+    if settings.max_concurrent_per_host == 0 {
+        return Err(anyhow!("Maximum concurrent requests per host must be at least 1"));
+    }
+    Ok(())
+}
+
+/// Identifies a host by name and port, so `http://a` and `https://a` are
+/// tracked separately but `https://a/x` and `https://a/y` are not.
+pub fn host_key(url: &str) -> Result<String> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+    let host = parsed.host_str()
+        .ok_or_else(|| anyhow!("URL has no host: {}", url))?
+        .to_lowercase();
+    Ok(match parsed.port_or_known_default() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_key() {
+        assert_eq!(host_key("https://Example.com/a?b=c").unwrap(), "example.com:443");
+        assert_eq!(host_key("http://example.com:8080/").unwrap(), "example.com:8080");
+        assert!(host_key("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_min_delay_between_requests_to_same_host() {
+        let politeness = Politeness::new(ScraperSettings {
+            max_concurrent_requests: 4,
+            max_concurrent_per_host: 2,
+            min_host_delay_ms: 100,
+            respect_robots_txt: true,
+        });
+
+        let started = Instant::now();
+        let _first = politeness.acquire("https://example.com/1", None).await.unwrap();
+        let _other_host = politeness.acquire("https://example.org/", None).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        let _second = politeness.acquire("https://example.com/2", None).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+}
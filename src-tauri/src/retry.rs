@@ -0,0 +1,96 @@
+use std::time::Duration;
+use crate::models::*;
+use crate::scraper::{HttpError, NetworkError};
+
+/// Decides whether a failed attempt should be retried and how long to wait.
+/// Returns `None` when the error is not retryable or the attempts are used
+/// up. A server's `Retry-After` is honoured but capped at `max_delay_ms`,
+/// like the backoff itself.
+pub fn next_retry_delay(policy: &RetryPolicy, attempt: u32, error: &anyhow::Error) -> Option<Duration> {
+    if attempt >= policy.max_attempts {
+        return None;
+    }
+
+    let max_delay = Duration::from_millis(policy.max_delay_ms);
+
+    if let Some(http_error) = error.downcast_ref::<HttpError>() {
+        if !policy.retry_on_status.contains(&http_error.status) {
+            return None;
+        }
+        if let Some(retry_after) = http_error.retry_after {
+            return Some(retry_after.min(max_delay));
+        }
+    } else if let Some(network_error) = error.downcast_ref::<NetworkError>() {
+        if !policy.retry_on_errors.contains(&network_error.kind) {
+            return None;
+        }
+    } else {
+        // Invalid selectors, proxy settings and the like won't fix themselves
+        return None;
+    }
+
+    Some(backoff_delay(policy, attempt))
+}
+
+/// Exponential backoff: `base_delay_ms * 2^(attempt - 1)`, capped at `max_delay_ms`.
+pub fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(32);
+    let delay = policy.base_delay_ms.saturating_mul(1u64 << exponent);
+    Duration::from_millis(delay.min(policy.max_delay_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay_ms: 1_000,
+            max_delay_ms: 5_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let policy = policy();
+        assert_eq!(backoff_delay(&policy, 1), Duration::from_secs(1));
+        assert_eq!(backoff_delay(&policy, 2), Duration::from_secs(2));
+        assert_eq!(backoff_delay(&policy, 3), Duration::from_secs(4));
+        assert_eq!(backoff_delay(&policy, 4), Duration::from_secs(5));
+        assert_eq!(backoff_delay(&policy, 100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_next_retry_delay() {
+        let policy = policy();
+
+        let server_error = anyhow::Error::new(HttpError { status: 502, retry_after: None });
+        assert_eq!(next_retry_delay(&policy, 1, &server_error), Some(Duration::from_secs(1)));
+        assert_eq!(next_retry_delay(&policy, 4, &server_error), None);
+
+        let not_found = anyhow::Error::new(HttpError { status: 404, retry_after: None });
+        assert_eq!(next_retry_delay(&policy, 1, &not_found), None);
+
+        let throttled = anyhow::Error::new(HttpError { status: 429, retry_after: Some(Duration::from_secs(3)) });
+        assert_eq!(next_retry_delay(&policy, 1, &throttled), Some(Duration::from_secs(3)));
+
+        // e.g. a robots.txt server error, which carries context about the URL
+        let robots_error = anyhow::Error::new(HttpError { status: 503, retry_after: None })
+            .context("https://example.com/robots.txt returned 503 Service Unavailable");
+        assert_eq!(next_retry_delay(&policy, 1, &robots_error), Some(Duration::from_secs(1)));
+
+        let throttled_long = anyhow::Error::new(HttpError { status: 503, retry_after: Some(Duration::from_secs(60)) });
+        assert_eq!(next_retry_delay(&policy, 1, &throttled_long), Some(Duration::from_secs(5)));
+
+        let timeout = anyhow::Error::new(NetworkError {
+            kind: NetworkErrorKind::Timeout,
+            message: "Failed to fetch URL: timed out".to_string(),
+        });
+        assert_eq!(next_retry_delay(&policy, 2, &timeout), Some(Duration::from_secs(2)));
+
+        let other = anyhow::anyhow!("Invalid CSS selector");
+        assert_eq!(next_retry_delay(&policy, 1, &other), None);
+    }
+}
@@ -0,0 +1,281 @@
+use reqwest::Client;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::politeness::Politeness;
+use crate::scraper::{HttpError, NetworkError};
+use anyhow::{Result, anyhow};
+use log::info;
+
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A run refused because robots.txt disallows the URL for the job's user
+/// agent.
+#[derive(Debug)]
+pub struct RobotsBlocked {
+    pub message: String,
+}
+
+impl std::fmt::Display for RobotsBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Blocked by robots.txt: {}", self.message)
+    }
+}
+
+impl std::error::Error for RobotsBlocked {}
+
+/// The rules of robots.txt that apply to one user agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    rules: Vec<(bool, String)>,
+    pub crawl_delay: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        RobotsRules::default()
+    }
+
+    /// Picks the group whose user-agent token is the longest one contained
+    /// in `user_agent`, falling back to the `*` group.
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agent_lines = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agent_lines || groups.is_empty() {
+                        groups.push(Group::default());
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_lowercase());
+                    }
+                    in_agent_lines = true;
+                }
+                "allow" | "disallow" => {
+                    in_agent_lines = false;
+                    // An empty Disallow allows everything, which is the default
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_agent_lines = false;
+                    if let (Some(group), Ok(seconds)) = (groups.last_mut(), value.parse::<f64>()) {
+                        if seconds.is_finite() && seconds >= 0.0 {
+                            group.crawl_delay = Some(Duration::from_secs_f64(seconds));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let user_agent = user_agent.to_lowercase();
+        let best = groups
+            .iter()
+            .filter_map(|group| {
+                group.agents
+                    .iter()
+                    .filter(|agent| *agent != "*" && !agent.is_empty() && user_agent.contains(agent.as_str()))
+                    .map(|agent| agent.len())
+                    .max()
+                    .map(|len| (len, group))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, group)| group)
+            .or_else(|| groups.iter().find(|group| group.agents.iter().any(|agent| agent == "*")));
+
+        match best {
+            Some(group) => RobotsRules {
+                rules: group.rules.clone(),
+                crawl_delay: group.crawl_delay,
+            },
+            None => RobotsRules::allow_all(),
+        }
+    }
+
+    /// The longest matching pattern wins; on a tie Allow wins.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.rules {
+            if !pattern_matches(pattern, path) {
+                continue;
+            }
+            let candidate = (pattern.len(), *allow);
+            best = match best {
+                Some(current) if current.0 > candidate.0 || (current.0 == candidate.0 && current.1) => Some(current),
+                _ => Some(candidate),
+            };
+        }
+
+        best.is_none_or(|(_, allow)| allow)
+    }
+}
+
+// Patterns match from the start of the path; `*` matches any sequence and a
+// trailing `$` anchors the end.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(stripped) => (stripped, true),
+        None => (pattern, false),
+    };
+    let mut regex_pattern = String::from("^");
+    regex_pattern.push_str(&pattern.split('*').map(regex::escape).collect::<Vec<_>>().join(".*"));
+    if anchored {
+        regex_pattern.push('$');
+    }
+    Regex::new(&regex_pattern).map(|regex| regex.is_match(path)).unwrap_or(false)
+}
+
+/// Fetched robots.txt files by origin, kept for a day.
+#[derive(Default)]
+pub struct RobotsCache {
+    entries: Mutex<HashMap<String, (Instant, Option<String>)>>,
+}
+
+impl RobotsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the crawl delay for the URL, or a `RobotsBlocked` error when
+    /// the URL may not be fetched with this user agent. Fetching robots.txt
+    /// waits for the same politeness limits as any other request.
+    pub async fn check(&self, client: &Client, politeness: &Politeness, url: &str, user_agent: &str) -> Result<Option<Duration>> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+        let origin = parsed.origin().ascii_serialization();
+
+        let cached = {
+            let entries = self.entries.lock().unwrap();
+            entries.get(&origin)
+                .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+                .map(|(_, content)| content.clone())
+        };
+
+        let content = match cached {
+            Some(content) => content,
+            None => {
+                let content = fetch_robots_txt(client, politeness, &origin, user_agent).await?;
+                self.entries.lock().unwrap().insert(origin.clone(), (Instant::now(), content.clone()));
+                content
+            }
+        };
+
+        let rules = match content {
+            Some(content) => RobotsRules::parse(&content, user_agent),
+            None => RobotsRules::allow_all(),
+        };
+
+        let mut path = parsed.path().to_string();
+        if let Some(query) = parsed.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        if !rules.is_allowed(&path) {
+            return Err(RobotsBlocked {
+                message: format!("{} is disallowed for this user agent", path),
+            }.into());
+        }
+
+        Ok(rules.crawl_delay)
+    }
+}
+
+// `Ok(None)` means there is no robots.txt and everything is allowed. An
+// unreachable host or a server error is returned as the network or HTTP
+// error it is, so the run is retried like any other outage; failures are
+// not cached, so the next run tries again.
+async fn fetch_robots_txt(client: &Client, politeness: &Politeness, origin: &str, user_agent: &str) -> Result<Option<String>> {
+    let robots_url = format!("{}/robots.txt", origin);
+    let _permit = politeness.acquire(&robots_url, None).await?;
+    info!("Fetching {}", robots_url);
+
+    let response = client.get(&robots_url)
+        .header("User-Agent", user_agent)
+        .send()
+        .await
+        .map_err(|e| NetworkError::from_reqwest(&format!("Failed to fetch {}", robots_url), e))?;
+
+    let status = response.status();
+    if status.is_client_error() {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let error = anyhow::Error::new(HttpError::from_response(&response));
+        return Err(error.context(format!("{} returned {}", robots_url, status)));
+    }
+
+    let content = response.text().await
+        .map_err(|e| NetworkError::from_reqwest(&format!("Failed to read {}", robots_url), e))?;
+    Ok(Some(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = r#"
+# Example
+User-agent: *
+Disallow: /private/
+Allow: /private/public
+Disallow: /*.pdf$
+Crawl-delay: 2
+
+User-agent: BadBot
+User-agent: WorseBot
+Disallow: /
+"#;
+
+    #[test]
+    fn test_default_group() {
+        let rules = RobotsRules::parse(ROBOTS, "Mozilla/5.0 (compatible; ScraperBot/1.0)");
+        assert!(rules.is_allowed("/"));
+        assert!(rules.is_allowed("/products?page=2"));
+        assert!(!rules.is_allowed("/private/data"));
+        assert!(rules.is_allowed("/private/public/page"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?download=1"));
+        assert_eq!(rules.crawl_delay, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_specific_group() {
+        let rules = RobotsRules::parse(ROBOTS, "WorseBot/2.1");
+        assert!(!rules.is_allowed("/"));
+        assert!(rules.is_allowed("/robots.txt"));
+        assert_eq!(rules.crawl_delay, None);
+    }
+
+    #[test]
+    fn test_missing_rules() {
+        assert!(RobotsRules::parse("", "AnyBot").is_allowed("/anything"));
+        assert!(RobotsRules::parse("User-agent: *\nDisallow:\n", "AnyBot").is_allowed("/anything"));
+        assert!(!RobotsRules::parse("User-agent: *\nDisallow: /\n", "AnyBot").is_allowed("/anything"));
+    }
+}
@@ -6,27 +6,52 @@ use uuid::Uuid;
 use std::collections::HashMap;
 use crate::models::*;
 use crate::database::Database;
-use crate::scraper::WebScraper;
+use crate::scraper::{HttpError, LoginError, NetworkError, WebScraper};
+use crate::robots::RobotsBlocked;
+use crate::session::CookieJar;
+use crate::changes::annotate_changes;
+use crate::alerts::{evaluate_alerts, format_alert_email};
+use crate::email::EmailService;
+use crate::retry::next_retry_delay;
+use crate::validation;
+use crate::health;
 use anyhow::Result;
 use log::{info, error, warn};
 
 pub struct ScrapingScheduler {
     scheduler: JobScheduler,
     db: Arc<Mutex<Database>>,
-    scraper: Arc<WebScraper>,
+    runner: JobRunner,
     job_handles: Arc<Mutex<HashMap<i64, Uuid>>>,
 }
 
+/// The execution pipeline shared by scheduled and manual runs: scrape,
+/// record the outcome whatever it is, detect changes and send alerts.
+#[derive(Clone)]
+pub struct JobRunner {
+    db: Arc<Mutex<Database>>,
+    scraper: Arc<WebScraper>,
+    email_service: Arc<Mutex<EmailService>>,
+}
+
 impl ScrapingScheduler {
-    pub async fn new(db: Arc<Mutex<Database>>) -> Result<Self> {
+    pub async fn new(
+        db: Arc<Mutex<Database>>,
+        scraper: Arc<WebScraper>,
+        email_service: Arc<Mutex<EmailService>>,
+    ) -> Result<Self> {
         let scheduler = JobScheduler::new().await?;
-        let scraper = Arc::new(WebScraper::new());
+        let runner = JobRunner {
+            db: Arc::clone(&db),
+            scraper,
+            email_service,
+        };
         let job_handles = Arc::new(Mutex::new(HashMap::new()));
         
         Ok(ScrapingScheduler {
             scheduler,
             db,
-            scraper,
+            runner,
             job_handles,
         })
     }
@@ -88,17 +113,15 @@ impl ScrapingScheduler {
         // Remove existing job if it exists
         self.unschedule_job(job_id).await?;
         
-        let db_clone = Arc::clone(&self.db);
-        let scraper_clone = Arc::clone(&self.scraper);
+        let runner_clone = self.runner.clone();
         let job_clone = job.clone();
         
         let scheduled_job = Job::new_async(cron_expression.as_str(), move |_uuid, _l| {
-            let db = Arc::clone(&db_clone);
-            let scraper = Arc::clone(&scraper_clone);
+            let runner = runner_clone.clone();
             let job = job_clone.clone();
             
             Box::pin(async move {
-                if let Err(e) = execute_scraping_job(db, scraper, job).await {
+                if let Err(e) = runner.execute(&job, RunTrigger::Scheduled).await {
                     error!("Failed to execute scraping job: {}", e);
                 }
             })
@@ -137,29 +160,10 @@ impl ScrapingScheduler {
         Ok(())
     }
     
-    pub async fn run_job_now(&self, job: ScrapingJob) -> Result<Vec<String>> {
-        info!("Running job immediately: {}", job.name);
-        
-        let results = self.scraper.scrape_job(&job).await?;
-        
-        // Save results to database
-        let db = self.db.lock().await;
-        let job_id = job.id.ok_or_else(|| anyhow::anyhow!("Job must have an ID"))?;
-        
-        let result = ScrapingResult {
-            id: None,
-            job_id,
-            scraped_data: results.join("\n"),
-            timestamp: Utc::now(),
-            success: true,
-            error_message: None,
-        };
-        
-        db.save_result(&result)?;
-        drop(db);
-        
-        info!("Job completed successfully: {}", job.name);
-        Ok(results)
+    /// The pipeline used for scheduled runs. Manual runs take a copy so they
+    /// don't keep the scheduler locked while they fetch and retry.
+    pub fn runner(&self) -> JobRunner {
+        self.runner.clone()
     }
     
     pub async fn get_scheduled_jobs(&self) -> Vec<i64> {
@@ -179,46 +183,242 @@ impl ScrapingScheduler {
     }
 }
 
-async fn execute_scraping_job(
-    db: Arc<Mutex<Database>>,
-    scraper: Arc<WebScraper>,
-    job: ScrapingJob,
-) -> Result<()> {
-    let job_id = job.id.ok_or_else(|| anyhow::anyhow!("Job must have an ID"))?;
+impl JobRunner {
+    /// Runs the job once and persists the outcome, including failures.
+    pub async fn execute(&self, job: &ScrapingJob, trigger: RunTrigger) -> Result<ScrapingResult> {
+        let job_id = job.id.ok_or_else(|| anyhow::anyhow!("Job must have an ID"))?;
+        
+        info!("Executing {} job: {} (ID: {})", trigger, job.name, job_id);
+        
+        // Jobs with a login reuse their session cookies across runs
+        let mut session = match job.login {
+            Some(_) => self.db.lock().await.get_cookie_jar(job_id)?,
+            None => CookieJar::default(),
+        };
+        
+        let started_at = Utc::now();
+        let mut attempts = Vec::new();
+        
+        let outcome = loop {
+            let attempt = attempts.len() as u32 + 1;
+            let attempt_started_at = Utc::now();
+            let outcome = self.scraper.scrape_job_with_session(job, &mut session).await;
+            
+            attempts.push(RunAttempt {
+                attempt,
+                started_at: attempt_started_at,
+                duration_ms: (Utc::now() - attempt_started_at).num_milliseconds(),
+                http_status: match &outcome {
+                    Ok(data) => data.http_status,
+                    Err(e) => e.downcast_ref::<HttpError>().map(|e| e.status),
+                },
+                error_message: outcome.as_ref().err().map(|e| e.to_string()),
+            });
+            
+            let delay = match &outcome {
+                Ok(_) => None,
+                Err(e) => next_retry_delay(&job.retry_policy, attempt, e),
+            };
+            match delay {
+                Some(delay) => {
+                    warn!("Attempt {} of job {} failed, retrying in {:?}", attempt, job.name, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                None => break outcome,
+            }
+        };
+        let finished_at = Utc::now();
+        
+        let mut result = ScrapingResult {
+            job_id,
+            timestamp: finished_at,
+            started_at: Some(started_at),
+            finished_at: Some(finished_at),
+            duration_ms: Some((finished_at - started_at).num_milliseconds()),
+            trigger,
+            attempts,
+            ..Default::default()
+        };
+        
+        match outcome {
+            Ok(mut data) => {
+                result.http_status = data.http_status;
+                result.bytes_fetched = Some(data.bytes_fetched as i64);
+                result.snapshots = std::mem::take(&mut data.snapshots);
+                let count = data.len();
+                record_data(job, &mut result, data);
+                match &result.error_message {
+                    None => info!("Job {} completed successfully with {} items", job.name, count),
+                    Some(message) => warn!("Job {} failed: {}", job.name, message),
+                }
+            }
+            Err(e) => {
+                error!("Job {} failed: {}", job.name, e);
+                result.success = false;
+                result.error_message = Some(e.to_string());
+                result.http_status = e.downcast_ref::<HttpError>().map(|e| e.status);
+                result.failure_reason = Some(failure_reason(&e));
+            }
+        }
+        
+        // Save result to database
+        let db = self.db.lock().await;
+        let previous = db.get_last_successful_result(job_id)?;
+        annotate_changes(&mut result, previous.as_ref());
+        result.id = Some(db.save_result(&result)?);
+        if job.login.is_some() {
+            db.save_cookie_jar(job_id, &session)?;
+        }
+        // One run more than the window, to compare with the health before this run
+        let history = db.get_run_history(job_id, health::HEALTH_WINDOW + 1)?;
+        drop(db);
+        
+        send_alerts(&self.email_service, job, &result).await;
+        if let Some(job_health) = health::newly_degraded(job_id, &history) {
+            send_health_warning(&self.email_service, job, &job_health).await;
+        }
+        
+        Ok(result)
+    }
     
-    info!("Executing scheduled job: {} (ID: {})", job.name, job_id);
+    pub async fn run_now(&self, job: &ScrapingJob) -> Result<Vec<String>> {
+        info!("Running job immediately: {}", job.name);
+        
+        let result = self.execute(job, RunTrigger::Manual).await?;
+        
+        if !result.success {
+            return Err(anyhow::anyhow!(result.error_message.unwrap_or_default()));
+        }
+        
+        info!("Job completed successfully: {}", job.name);
+        Ok(result.item_values())
+    }
     
-    let result = match scraper.scrape_job(&job).await {
-        Ok(data) => {
-            info!("Job {} completed successfully with {} items", job.name, data.len());
-            ScrapingResult {
-                id: None,
-                job_id,
-                scraped_data: data.join("\n"),
-                timestamp: Utc::now(),
-                success: true,
-                error_message: None,
+    /// Extracts every run of the job that has stored pages again, with the
+    /// job's current selectors, transforms and validation rules, and
+    /// replaces the items stored for those runs. Runs are processed oldest
+    /// first so each one's changes are computed against the updated data.
+    pub async fn reextract_snapshots(&self, job: &ScrapingJob) -> Result<BackfillSummary> {
+        info!("Re-extracting stored pages of job: {}", job.name);
+        let job_id = job.id.ok_or_else(|| anyhow::anyhow!("Job must have an ID"))?;
+        let mut summary = BackfillSummary::default();
+        
+        let result_ids = self.db.lock().await.get_result_ids_with_snapshots(job_id)?;
+        for result_id in result_ids {
+            // The database is only locked to read and write, not while the
+            // stored pages are extracted
+            let (result, snapshots) = {
+                let db = self.db.lock().await;
+                (db.get_result(result_id)?, db.get_result_snapshots(result_id)?)
+            };
+            let Some(mut result) = result else {
+                continue;
+            };
+            
+            result.error_message = None;
+            result.failure_reason = None;
+            result.content_hash = None;
+            result.changed = None;
+            result.diff = None;
+            match self.scraper.reextract(job, &snapshots) {
+                Ok(data) => record_data(job, &mut result, data),
+                Err(e) => {
+                    warn!("Failed to re-extract run {} of job {}: {}", result_id, job.name, e);
+                    result.success = false;
+                    result.error_message = Some(e.to_string());
+                    result.failure_reason = Some(FailureReason::Other);
+                    result.scraped_data = String::new();
+                    result.items = Vec::new();
+                    result.records = Vec::new();
+                }
             }
-        }
-        Err(e) => {
-            error!("Job {} failed: {}", job.name, e);
-            ScrapingResult {
-                id: None,
-                job_id,
-                scraped_data: String::new(),
-                timestamp: Utc::now(),
-                success: false,
-                error_message: Some(e.to_string()),
+            
+            let db = self.db.lock().await;
+            let previous = db.get_last_successful_result_before(job_id, &result)?;
+            annotate_changes(&mut result, previous.as_ref());
+            db.replace_result_data(&result)?;
+            drop(db);
+            
+            if result.success {
+                summary.results_updated += 1;
+            } else {
+                summary.results_failed += 1;
             }
         }
-    };
+        
+        info!(
+            "Re-extracted job {} from snapshots: {} run(s) updated, {} failed",
+            job.name, summary.results_updated, summary.results_failed
+        );
+        Ok(summary)
+    }
+}
+
+/// Stores extracted data on the result, marking it failed if the data
+/// breaks the job's validation rules. The data is kept either way so a
+/// page that broke the rules can be inspected.
+fn record_data(job: &ScrapingJob, result: &mut ScrapingResult, data: ScrapedData) {
+    let violations = validation::check(&job.validation, job.number_locale(), &data);
+    if violations.is_empty() {
+        result.success = true;
+    } else {
+        result.success = false;
+        result.error_message = Some(format!("Validation failed: {}", violations.join("; ")));
+        result.failure_reason = Some(FailureReason::ValidationFailed);
+    }
+    result.scraped_data = data.item_values().join("\n");
+    result.items = data.values;
+    result.records = data.records;
+}
+
+fn failure_reason(error: &anyhow::Error) -> FailureReason {
+    if error.is::<RobotsBlocked>() {
+        FailureReason::RobotsBlocked
+    } else if error.is::<LoginError>() {
+        FailureReason::LoginFailed
+    } else if error.is::<HttpError>() {
+        FailureReason::Http
+    } else if error.is::<NetworkError>() {
+        FailureReason::Network
+    } else {
+        FailureReason::Other
+    }
+}
+
+async fn send_health_warning(email_service: &Mutex<EmailService>, job: &ScrapingJob, job_health: &JobHealth) {
+    warn!("Job {} is degraded: {}", job.name, job_health.reasons.join("; "));
     
-    // Save result to database
-    let db = db.lock().await;
-    db.save_result(&result)?;
-    drop(db);
+    let email_service = email_service.lock().await;
+    if email_service.get_config().is_none() {
+        warn!("Job {} is degraded but email is not configured", job.name);
+        return;
+    }
+    
+    let (subject, message) = health::format_health_email(job, job_health);
+    if let Err(e) = email_service.send_notification(&subject, &message).await {
+        error!("Failed to send health warning for job {}: {}", job.name, e);
+    }
+}
+
+// Alert delivery problems are logged rather than failing the run
+async fn send_alerts(email_service: &Mutex<EmailService>, job: &ScrapingJob, result: &ScrapingResult) {
+    let triggers = evaluate_alerts(job, result);
+    if triggers.is_empty() {
+        return;
+    }
+    
+    info!("Job {} triggered {} alert(s)", job.name, triggers.len());
     
-    Ok(())
+    let email_service = email_service.lock().await;
+    if email_service.get_config().is_none() {
+        warn!("Alerts triggered for job {} but email is not configured", job.name);
+        return;
+    }
+    
+    let (subject, message) = format_alert_email(job, result, &triggers);
+    if let Err(e) = email_service.send_notification(&subject, &message).await {
+        error!("Failed to send alert for job {}: {}", job.name, e);
+    }
 }
 
 // Helper function to convert common schedule formats to cron expressions
@@ -1,65 +1,694 @@
 use reqwest::Client;
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use std::time::Duration;
 use crate::models::*;
+use crate::politeness::Politeness;
+use crate::robots::RobotsCache;
+use crate::session::CookieJar;
+use crate::credentials;
+use crate::pagination;
+use crate::xpath;
+use crate::jsonpath;
+use crate::metadata;
+use crate::transforms;
 use anyhow::{Result, anyhow};
 use log::{info, error, warn};
 
 pub struct WebScraper {
-    client: Client,
+    clients: Mutex<HashMap<ClientKey, Client>>,
+    politeness: Politeness,
+    robots: RobotsCache,
+}
+
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300); // 5 minutes timeout
+const MAX_REDIRECTS: usize = 10;
+
+/// The settings a `Client` is built with. Jobs with the same settings share
+/// one client, and with it the connection pool.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct ClientKey {
+    proxy_url: Option<String>,
+    accept_invalid_certs: bool,
+    /// Return redirects to the caller instead of following them.
+    manual_redirects: bool,
+}
+
+impl ClientKey {
+    fn for_job(job: &ScrapingJob) -> Self {
+        ClientKey {
+            proxy_url: job.proxy_url.clone().filter(|url| !url.trim().is_empty()),
+            accept_invalid_certs: job.accept_invalid_certs,
+            manual_redirects: false,
+        }
+    }
+
+    fn build_client(&self) -> Result<Client> {
+        let mut builder = Client::builder()
+            .user_agent(DEFAULT_USER_AGENT)
+            .timeout(REQUEST_TIMEOUT)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        
+        if self.manual_redirects {
+            builder = builder.redirect(reqwest::redirect::Policy::none());
+        }
+        
+        if let Some(proxy_url) = &self.proxy_url {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| anyhow!("Invalid proxy URL: {}", e))?;
+            builder = builder.proxy(proxy);
+        }
+        
+        builder.build()
+            .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))
+    }
+}
+
+/// A non-success HTTP response, kept as a typed error so callers can record
+/// the status code of failed runs and decide whether to retry.
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: u16,
+    /// Parsed `Retry-After` header of 429 and 503 responses.
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpError {
+    pub fn from_response(response: &reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = match status {
+            429 | 503 => response.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after),
+            _ => None,
+        };
+        HttpError { status, retry_after }
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match reqwest::StatusCode::from_u16(self.status) {
+            Ok(status) => write!(f, "HTTP error: {}", status),
+            Err(_) => write!(f, "HTTP error: {}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// A request that failed before a response was received.
+#[derive(Debug)]
+pub struct NetworkError {
+    pub kind: NetworkErrorKind,
+    pub message: String,
+}
+
+impl NetworkError {
+    pub fn from_reqwest(context: &str, error: reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            NetworkErrorKind::Timeout
+        } else if error.is_connect() {
+            NetworkErrorKind::Connect
+        } else {
+            NetworkErrorKind::Other
+        };
+        NetworkError { kind, message: format!("{}: {}", context, error) }
+    }
+}
+
+impl std::fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// The login step failed, or the site still treats us as logged out.
+#[derive(Debug)]
+pub struct LoginError {
+    pub message: String,
+}
+
+impl std::fmt::Display for LoginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Login failed: {}", self.message)
+    }
+}
+
+impl std::error::Error for LoginError {}
+
+struct FetchedPage {
+    status: u16,
+    /// The URL after redirects.
+    url: reqwest::Url,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl FetchedPage {
+    fn snapshot(&self, kind: SnapshotKind) -> PageSnapshot {
+        PageSnapshot {
+            kind,
+            url: self.url.to_string(),
+            status: self.status,
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+        }
+    }
+    
+    fn from_snapshot(snapshot: &PageSnapshot) -> Result<Self> {
+        Ok(FetchedPage {
+            status: snapshot.status,
+            url: reqwest::Url::parse(&snapshot.url)
+                .map_err(|e| anyhow!("Invalid snapshot URL '{}': {}", snapshot.url, e))?,
+            headers: snapshot.headers.clone(),
+            body: snapshot.body.clone(),
+        })
+    }
 }
 
 impl WebScraper {
     pub fn new() -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(300)) // 5 minutes timeout
-            .build()
-            .expect("Failed to create HTTP client");
+        let default_key = ClientKey::default();
+        let client = default_key.build_client().expect("Failed to create HTTP client");
         
-        WebScraper { client }
+        WebScraper {
+            clients: Mutex::new(HashMap::from([(default_key, client)])),
+            politeness: Politeness::default(),
+            robots: RobotsCache::new(),
+        }
+    }
+    
+    pub fn politeness(&self) -> &Politeness {
+        &self.politeness
     }
 
-    pub async fn scrape_job(&self, job: &ScrapingJob) -> Result<Vec<String>> {
+    fn client_for(&self, key: &ClientKey) -> Result<Client> {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(key) {
+            return Ok(client.clone());
+        }
+        
+        let client = key.build_client()?;
+        clients.insert(key.clone(), client.clone());
+        Ok(client)
+    }
+
+    /// Scrapes with the given session cookies, logging in first when the job
+    /// has a login step and no live session, and again if the session turns
+    /// out to have expired. Cookies set by the site are stored in `session`.
+    pub async fn scrape_job_with_session(&self, job: &ScrapingJob, session: &mut CookieJar) -> Result<ScrapedData> {
+        let mut data = self.scrape_untransformed(job, session).await?;
+        transforms::apply_to_data(job, &mut data)?;
+        Ok(data)
+    }
+    
+    /// Fetches and extracts everything the job asks for, but leaves the
+    /// values as they appear on the page.
+    async fn scrape_untransformed(&self, job: &ScrapingJob, session: &mut CookieJar) -> Result<ScrapedData> {
         info!("Starting scrape for job: {} ({})", job.name, job.url);
         
-        let mut request = self.client.get(&job.url);
+        let job = &with_request_secrets(job)?;
+        let client = self.client_for(&ClientKey::for_job(job))?;
+        let page = self.fetch_first_page(&client, job, session).await?;
         
-        // Set custom user agent if provided
-        if let Some(user_agent) = &job.user_agent {
-            request = request.header("User-Agent", user_agent);
-        } else {
-            request = request.header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36");
+        let mut data = self.extract_page(job, &page)?;
+        data.http_status = Some(page.status);
+        data.bytes_fetched = page.body.len() as u64;
+        if job.store_snapshots {
+            data.snapshots.push(page.snapshot(SnapshotKind::Page));
         }
         
-        // Set proxy if provided
-        if let Some(proxy_url) = &job.proxy_url {
-            let proxy = reqwest::Proxy::all(proxy_url)
-                .map_err(|e| anyhow!("Invalid proxy URL: {}", e))?;
-            let client_with_proxy = Client::builder()
-                .proxy(proxy)
-                .timeout(Duration::from_secs(300)) // 5 minutes timeout
-                .build()
-                .map_err(|e| anyhow!("Failed to create client with proxy: {}", e))?;
-            request = client_with_proxy.get(&job.url);
-            if let Some(user_agent) = &job.user_agent {
-                request = request.header("User-Agent", user_agent);
+        if let Some(spec) = &job.pagination {
+            self.follow_pages(&client, job, spec, page, &mut data, session).await?;
+        }
+        
+        if let Some(crawl) = &job.crawl {
+            let links = std::mem::take(&mut data.values);
+            let details = self.crawl_details(&client, job, crawl, links, session).await?;
+            data.values = details.values;
+            data.records = details.records;
+            data.bytes_fetched += details.bytes_fetched;
+            data.snapshots.extend(details.snapshots);
+        }
+        Ok(data)
+    }
+    
+    /// Fetches the job's first page as a run would, with the job's request
+    /// settings, session and the politeness limits, and returns its body.
+    pub async fn fetch_job_page(&self, job: &ScrapingJob, session: &mut CookieJar) -> Result<String> {
+        let job = &with_request_secrets(job)?;
+        let client = self.client_for(&ClientKey::for_job(job))?;
+        Ok(self.fetch_first_page(&client, job, session).await?.body)
+    }
+    
+    /// Fetches the first page of the job, logging in first when the job has
+    /// a login step and no live session, and again if the session turns out
+    /// to have expired.
+    async fn fetch_first_page(&self, client: &Client, job: &ScrapingJob, session: &mut CookieJar) -> Result<FetchedPage> {
+        let mut logged_in = false;
+        if let Some(login) = &job.login {
+            if !session.has_cookies() {
+                self.log_in(job, login, session).await?;
+                logged_in = true;
+            }
+        }
+        
+        let first_url = match &job.pagination {
+            Some(spec) => pagination::page_url(&spec.mode, &job.url, 0, &job.url, "")?
+                .unwrap_or_else(|| job.url.clone()),
+            None => job.url.clone(),
+        };
+        
+        let outcome = self.fetch_page(client, job, &first_url, session).await;
+        let page = match &job.login {
+            Some(login) if !logged_in && session_expired(login, &outcome) => {
+                info!("Session for job {} has expired, logging in again", job.name);
+                session.clear();
+                self.log_in(job, login, session).await?;
+                self.fetch_page(client, job, &first_url, session).await
+            }
+            _ => outcome,
+        };
+        
+        if let Some(login) = &job.login {
+            if session_expired(login, &page) {
+                return Err(LoginError {
+                    message: "still logged out after logging in".to_string(),
+                }.into());
+            }
+        }
+        page
+    }
+    
+    /// Extracts a stored run again from its snapshots with the job's
+    /// current selectors and transforms. Crawling jobs use their detail
+    /// pages; the index pages only held links.
+    pub fn reextract(&self, job: &ScrapingJob, snapshots: &[PageSnapshot]) -> Result<ScrapedData> {
+        let kind = if job.crawl.is_some() { SnapshotKind::Detail } else { SnapshotKind::Page };
+        let url_field = job.crawl.as_ref().and_then(|crawl| crawl.url_field.as_ref());
+        
+        // Like a live run, the status is the one of the job's own page
+        let mut data = ScrapedData {
+            http_status: snapshots
+                .iter()
+                .find(|snapshot| snapshot.kind == SnapshotKind::Page)
+                .map(|snapshot| snapshot.status),
+            ..Default::default()
+        };
+        for snapshot in snapshots.iter().filter(|snapshot| snapshot.kind == kind) {
+            let page = FetchedPage::from_snapshot(snapshot)?;
+            let mut page_data = self.extract_from(job, &page)?;
+            if let Some(url_field) = url_field {
+                for record in &mut page_data.records {
+                    record.insert(url_field.clone(), snapshot.url.clone());
+                }
             }
+            data.bytes_fetched += page.body.len() as u64;
+            data.values.extend(page_data.values);
+            data.records.extend(page_data.records);
         }
         
-        let response = request.send().await
-            .map_err(|e| anyhow!("Failed to fetch URL: {}", e))?;
+        transforms::apply_to_data(job, &mut data)?;
+        Ok(data)
+    }
+    
+    /// Items of a page fetched from the job URL or its pagination: the
+    /// detail links for crawling jobs, the extracted items otherwise.
+    fn extract_page(&self, job: &ScrapingJob, page: &FetchedPage) -> Result<ScrapedData> {
+        match &job.crawl {
+            Some(crawl) => Ok(ScrapedData {
+                values: extract_links(&page.body, &page.url, crawl)?,
+                ..Default::default()
+            }),
+            None => self.extract_from(job, page),
+        }
+    }
+    
+    /// Runs the job's selectors against a fetched page and applies the
+    /// job's URL options, which need to know where the page came from.
+    fn extract_from(&self, job: &ScrapingJob, page: &FetchedPage) -> Result<ScrapedData> {
+        let mut data = self.extract(job, &page.body)?;
         
-        if !response.status().is_success() {
-            return Err(anyhow!("HTTP error: {}", response.status()));
+        let options = &job.url_options;
+        if !(options.resolve || options.dedupe || options.same_host_only) {
+            return Ok(data);
         }
         
-        let html_content = response.text().await
-            .map_err(|e| anyhow!("Failed to read response body: {}", e))?;
+        let base = document_base_url(&page.body, &page.url);
+        if is_url_attribute(&job.data_type) {
+            data.values = apply_url_options(options, &base, &page.url, std::mem::take(&mut data.values));
+        }
         
-        match job.selector_type {
-            SelectorType::CSS => self.scrape_with_css(&html_content, &job.selector, &job.data_type),
-            SelectorType::Regex => self.scrape_with_regex(&html_content, &job.selector),
+        let url_fields: Vec<&str> = job.fields.iter()
+            .filter(|field| is_url_attribute(&field.data_type))
+            .map(|field| field.name.as_str())
+            .collect();
+        for record in &mut data.records {
+            for name in &url_fields {
+                if let Some(value) = record.get_mut(*name) {
+                    *value = apply_url_option(options, &base, &page.url, std::mem::take(value))
+                        .unwrap_or_default();
+                }
+            }
+        }
+        // Records are duplicates when all their URL fields match; records
+        // without any URL are always kept
+        if options.dedupe && !url_fields.is_empty() {
+            let mut seen = HashSet::new();
+            data.records.retain(|record| {
+                let urls: Vec<String> = url_fields.iter()
+                    .map(|name| record.get(*name).cloned().unwrap_or_default())
+                    .collect();
+                urls.iter().all(|url| url.is_empty()) || seen.insert(urls)
+            });
+        }
+        
+        Ok(data)
+    }
+    
+    /// Fetches each detail page and applies the job's selectors to it. A
+    /// detail page that fails is skipped; the run fails only if all do.
+    async fn crawl_details(
+        &self,
+        client: &Client,
+        job: &ScrapingJob,
+        crawl: &CrawlSpec,
+        links: Vec<String>,
+        session: &mut CookieJar,
+    ) -> Result<ScrapedData> {
+        let mut seen = HashSet::new();
+        let links: Vec<String> = links.into_iter().filter(|link| seen.insert(link.clone())).collect();
+        let limit = crawl.max_links as usize;
+        if links.len() > limit {
+            info!("Job {} found {} links, following the first {}", job.name, links.len(), limit);
+        }
+        
+        let mut data = ScrapedData::default();
+        let mut extracted = 0;
+        let mut last_error = None;
+        
+        for link in links.into_iter().take(limit) {
+            let page = match self.fetch_page(client, job, &link, session).await {
+                Ok(page) => page,
+                Err(e) => {
+                    warn!("Failed to fetch detail page {}: {}", link, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            data.bytes_fetched += page.body.len() as u64;
+            if job.store_snapshots {
+                data.snapshots.push(page.snapshot(SnapshotKind::Detail));
+            }
+            
+            let mut detail = match self.extract_from(job, &page) {
+                Ok(detail) => detail,
+                Err(e) => {
+                    warn!("Failed to extract detail page {}: {}", link, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            extracted += 1;
+            if let Some(url_field) = &crawl.url_field {
+                for record in &mut detail.records {
+                    record.insert(url_field.clone(), link.clone());
+                }
+            }
+            data.values.extend(detail.values);
+            data.records.extend(detail.records);
+        }
+        
+        match last_error {
+            Some(e) if extracted == 0 => Err(e),
+            _ => Ok(data),
+        }
+    }
+    
+    /// Fetches the pages after `first` and appends their items to `data`
+    /// until a stop condition of `spec` is met. A page that fails to load or
+    /// extract ends pagination; the pages before it are kept.
+    async fn follow_pages(
+        &self,
+        client: &Client,
+        job: &ScrapingJob,
+        spec: &PaginationSpec,
+        first: FetchedPage,
+        data: &mut ScrapedData,
+        session: &mut CookieJar,
+    ) -> Result<()> {
+        let mut seen: HashSet<String> = data.item_values().into_iter().collect();
+        let mut visited = HashSet::from([first.url.to_string()]);
+        let mut current = first;
+        
+        for index in 1..spec.max_pages {
+            let url = match pagination::page_url(&spec.mode, &job.url, index, current.url.as_str(), &current.body)? {
+                Some(url) => url,
+                None => {
+                    info!("No next page after page {} of job {}", index, job.name);
+                    break;
+                }
+            };
+            if !visited.insert(url.clone()) {
+                warn!("Pagination of job {} returned to {}, stopping", job.name, url);
+                break;
+            }
+            
+            let fetched = self.fetch_page(client, job, &url, session).await
+                .and_then(|page| Ok((self.extract_page(job, &page)?, page)));
+            let (page_data, page) = match fetched {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    warn!("Page {} of job {} failed, keeping earlier pages: {}", index + 1, job.name, e);
+                    break;
+                }
+            };
+            data.bytes_fetched += page.body.len() as u64;
+            
+            let lines = page_data.item_values();
+            let new_items = lines.iter().filter(|line| !seen.contains(*line)).count();
+            if page_data.is_empty() || (spec.stop_on_no_new_items && new_items == 0) {
+                info!("Page {} of job {} has no new items, stopping", index + 1, job.name);
+                break;
+            }
+            
+            seen.extend(lines);
+            data.values.extend(page_data.values);
+            data.records.extend(page_data.records);
+            if job.store_snapshots {
+                data.snapshots.push(page.snapshot(SnapshotKind::Page));
+            }
+            current = page;
+        }
+        
+        Ok(())
+    }
+    
+    /// Fetches one page for the job, honouring robots.txt and politeness
+    /// limits. Non-success responses become `HttpError`s. Redirects are
+    /// followed by hand so that cookies set on each hop reach `session`,
+    /// as sites often refresh a session on the way to a landing page.
+    async fn fetch_page(&self, client: &Client, job: &ScrapingJob, url: &str, session: &mut CookieJar) -> Result<FetchedPage> {
+        let respect_robots_txt = self.politeness.settings().respect_robots_txt;
+        let user_agent = job.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+        
+        let redirect_client = self.client_for(&ClientKey { manual_redirects: true, ..ClientKey::for_job(job) })?;
+        let first_url = reqwest::Url::parse(url)
+            .map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+        let mut current_url = first_url.clone();
+        let mut request = job_request(&redirect_client, job, url, session)?;
+        let mut redirects = 0;
+        
+        let (response, _permit) = loop {
+            // Every hop may land on another host with its own rules and delay
+            let crawl_delay = if respect_robots_txt {
+                self.robots.check(client, &self.politeness, current_url.as_str(), user_agent).await?
+            } else {
+                None
+            };
+            
+            // Wait for a free slot for this host; held until the body is read
+            let permit = self.politeness.acquire(current_url.as_str(), crawl_delay).await?;
+            
+            let response = request.send().await
+                .map_err(|e| NetworkError::from_reqwest("Failed to fetch URL", e))?;
+            session.store_response_cookies(&current_url, response.headers());
+            
+            let status = response.status();
+            let location = response.headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok());
+            let Some(location) = location.filter(|_| status.is_redirection()) else {
+                break (response, permit);
+            };
+            
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(anyhow!("Too many redirects fetching {}", url));
+            }
+            current_url = current_url.join(location)
+                .map_err(|e| anyhow!("Invalid redirect '{}' from {}: {}", location, current_url, e))?;
+            // Like browsers, only 307 and 308 repeat the method and body
+            let keep_method = matches!(status.as_u16(), 307 | 308);
+            let same_origin = current_url.origin() == first_url.origin();
+            request = redirect_request(&redirect_client, job, &current_url, keep_method, same_origin, session)?;
+        };
+        
+        let final_url = current_url;
+        let headers = response.headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        
+        let status = response.status();
+        if !status.is_success() {
+            return Err(HttpError::from_response(&response).into());
+        }
+        
+        let body = response.text().await
+            .map_err(|e| NetworkError::from_reqwest("Failed to read response body", e))?;
+        
+        Ok(FetchedPage { status: status.as_u16(), url: final_url, headers, body })
+    }
+    
+    /// Submits the login form and collects the session cookies. Redirects
+    /// are followed by hand because login responses usually set their
+    /// cookies on the redirect itself. robots.txt is not consulted: login
+    /// forms are commonly disallowed for crawlers but are not crawled here.
+    async fn log_in(&self, job: &ScrapingJob, login: &LoginSpec, session: &mut CookieJar) -> Result<()> {
+        let login_error = |message: String| LoginError { message };
+        
+        let job_id = job.id.ok_or_else(|| login_error("save the job before logging in".to_string()))?;
+        let password = credentials::get_job_password(job_id)
+            .map_err(|e| login_error(e.to_string()))?;
+        
+        let client = self.client_for(&ClientKey { manual_redirects: true, ..ClientKey::for_job(job) })?;
+        
+        let mut form = login.extra_fields.clone();
+        form.insert(login.username_field.clone(), login.username.clone());
+        form.insert(login.password_field.clone(), password);
+        
+        let mut url = reqwest::Url::parse(&login.url)
+            .map_err(|e| login_error(format!("invalid login URL '{}': {}", login.url, e)))?;
+        let mut request = client.post(url.clone()).form(&form);
+        
+        info!("Logging in for job: {} ({})", job.name, url);
+        
+        for _ in 0..MAX_REDIRECTS {
+            if let Some(user_agent) = &job.user_agent {
+                request = request.header(reqwest::header::USER_AGENT, user_agent);
+            }
+            if let Some(cookies) = session.header_for(&url) {
+                request = request.header(reqwest::header::COOKIE, cookies);
+            }
+            
+            let _permit = self.politeness.acquire(url.as_str(), None).await?;
+            let response = request.send().await
+                .map_err(|e| NetworkError::from_reqwest("Failed to log in", e))?;
+            session.store_response_cookies(&url, response.headers());
+            
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response.headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| login_error(format!("login returned {} without a location", status)))?;
+                url = url.join(location)
+                    .map_err(|e| login_error(format!("invalid redirect '{}': {}", location, e)))?;
+                request = client.get(url.clone());
+                continue;
+            }
+            
+            if !status.is_success() {
+                return Err(login_error(format!("login returned {}", status)).into());
+            }
+            
+            let body = response.text().await
+                .map_err(|e| NetworkError::from_reqwest("Failed to read login response", e))?;
+            if login.logged_out_marker.as_ref().is_some_and(|marker| body.contains(marker.as_str())) {
+                return Err(login_error("the login form was shown again; check the username and password".to_string()).into());
+            }
+            if !session.has_cookies() {
+                return Err(login_error("the site did not set a session cookie".to_string()).into());
+            }
+            
+            info!("Logged in for job: {}", job.name);
+            return Ok(());
+        }
+        
+        Err(login_error("too many redirects".to_string()).into())
+    }
+    
+    /// Runs the job's selectors against an already fetched document.
+    pub fn extract(&self, job: &ScrapingJob, html: &str) -> Result<ScrapedData> {
+        if job.has_fields() && matches!(job.selector_type, SelectorType::JsonPath) {
+            let item_selector = job.item_selector.as_deref().filter(|s| !s.trim().is_empty());
+            let records = self.scrape_json_records(html, item_selector, &job.fields)?;
+            Ok(ScrapedData { records, ..Default::default() })
+        } else if job.has_fields() {
+            let item_selector = job.item_selector.as_deref().filter(|s| !s.trim().is_empty());
+            let records = self.scrape_records(html, item_selector, &job.fields)?;
+            Ok(ScrapedData { records, ..Default::default() })
+        } else {
+            let values = match job.selector_type {
+                SelectorType::CSS => self.scrape_with_css(html, &job.selector, &job.data_type)?,
+                SelectorType::Regex => self.scrape_with_regex(html, &job.selector)?,
+                SelectorType::XPath => self.scrape_with_xpath(html, &job.selector, &job.data_type)?,
+                SelectorType::JsonPath => self.scrape_with_jsonpath(html, &job.selector)?,
+                SelectorType::Metadata => self.scrape_with_metadata(html, &job.selector)?,
+            };
+            Ok(ScrapedData { values, ..Default::default() })
+        }
+    }
+    
+    /// Every match of a selector in a document, with where it was found.
+    /// Unlike `extract`, empty values are kept and nothing is logged, so
+    /// selectors can be tried out quickly against a saved page.
+    pub fn select_matches(&self, body: &str, selector_type: &SelectorType, selector: &str, data_type: &DataType) -> Result<Vec<SelectorMatch>> {
+        match selector_type {
+            SelectorType::CSS => {
+                let document = Html::parse_document(body);
+                let parsed = Selector::parse(selector)
+                    .map_err(|e| anyhow!("Invalid CSS selector '{}': {:?}", selector, e))?;
+                Ok(document
+                    .select(&parsed)
+                    .map(|element| SelectorMatch {
+                        path: css_path(element),
+                        value: extract_element_value(element, data_type),
+                    })
+                    .collect())
+            }
+            SelectorType::Regex => {
+                let regex = Regex::new(selector)
+                    .map_err(|e| anyhow!("Invalid regex pattern '{}': {}", selector, e))?;
+                Ok(regex
+                    .captures_iter(body)
+                    .filter_map(|captures| captured_match(&captures))
+                    .map(|matched| SelectorMatch {
+                        path: format!("offset {}", matched.start()),
+                        value: matched.as_str().to_string(),
+                    })
+                    .collect())
+            }
+            SelectorType::XPath => xpath::select_matches(body, selector, data_type),
+            SelectorType::JsonPath => jsonpath::select_matches(body, selector),
+            SelectorType::Metadata => {
+                // As with `scrape_with_metadata`, an empty key lists every pair
+                let pairs = metadata::extract_metadata(&Html::parse_document(body));
+                Ok(pairs
+                    .into_iter()
+                    .filter(|(name, _)| selector.trim().is_empty() || metadata::key_matches(name, selector))
+                    .map(|(path, value)| SelectorMatch { path, value })
+                    .collect())
+            }
         }
     }
     
@@ -71,16 +700,7 @@ impl WebScraper {
         let mut results = Vec::new();
         
         for element in document.select(&selector) {
-            let data = match data_type {
-                DataType::Text => {
-                    element.text().collect::<Vec<_>>().join(" ").trim().to_string()
-                }
-                DataType::Attribute(attr_name) => {
-                    element.value().attr(attr_name)
-                        .unwrap_or_default()
-                        .to_string()
-                }
-            };
+            let data = extract_element_value(element, data_type);
             
             if !data.is_empty() {
                 results.push(data);
@@ -96,6 +716,50 @@ impl WebScraper {
         Ok(results)
     }
     
+    /// Values stored under `key` in the page metadata. An empty key lists
+    /// every `key: value` pair, to help choose one.
+    fn scrape_with_metadata(&self, html: &str, key: &str) -> Result<Vec<String>> {
+        let pairs = metadata::extract_metadata(&Html::parse_document(html));
+        
+        let results = if key.trim().is_empty() {
+            pairs.iter().map(|(name, value)| format!("{}: {}", name, value)).collect()
+        } else {
+            metadata::values_for(&pairs, key)
+        };
+        
+        if results.is_empty() {
+            warn!("No metadata found for key: {}", key);
+        } else {
+            info!("Found {} metadata values", results.len());
+        }
+        
+        Ok(results)
+    }
+    
+    fn scrape_with_xpath(&self, html: &str, expression: &str, data_type: &DataType) -> Result<Vec<String>> {
+        let results = xpath::select_values(html, expression, data_type)?;
+        
+        if results.is_empty() {
+            warn!("No data found with XPath expression: {}", expression);
+        } else {
+            info!("Found {} items with XPath expression", results.len());
+        }
+        
+        Ok(results)
+    }
+    
+    fn scrape_with_jsonpath(&self, body: &str, expression: &str) -> Result<Vec<String>> {
+        let results = jsonpath::select_values(body, expression)?;
+        
+        if results.is_empty() {
+            warn!("No data found with JSONPath expression: {}", expression);
+        } else {
+            info!("Found {} items with JSONPath expression", results.len());
+        }
+        
+        Ok(results)
+    }
+    
     fn scrape_with_regex(&self, text: &str, pattern: &str) -> Result<Vec<String>> {
         let regex = Regex::new(pattern)
             .map_err(|e| anyhow!("Invalid regex pattern '{}': {}", pattern, e))?;
@@ -103,12 +767,7 @@ impl WebScraper {
         let mut results = Vec::new();
         
         for captures in regex.captures_iter(text) {
-            // If there are capture groups, use the first one; otherwise use the full match
-            let matched_text = if captures.len() > 1 {
-                captures.get(1).map(|m| m.as_str()).unwrap_or_default()
-            } else {
-                captures.get(0).map(|m| m.as_str()).unwrap_or_default()
-            };
+            let matched_text = captured_text(&captures);
             
             if !matched_text.is_empty() {
                 results.push(matched_text.to_string());
@@ -124,19 +783,152 @@ impl WebScraper {
         Ok(results)
     }
     
-    pub async fn test_scrape(&self, job: &ScrapingJob) -> Result<Vec<String>> {
+    fn scrape_records(&self, html: &str, item_selector: Option<&str>, fields: &[FieldSpec]) -> Result<Vec<ScrapedRecord>> {
+        let document = Html::parse_document(html);
+        
+        let compiled_fields = fields.iter()
+            .map(CompiledField::compile)
+            .collect::<Result<Vec<_>>>()?;
+        
+        // Metadata describes the whole page, so it is the same for every item
+        let page_metadata = if compiled_fields.iter().any(|field| matches!(field, CompiledField::Metadata(_))) {
+            metadata::extract_metadata(&document)
+        } else {
+            Vec::new()
+        };
+        
+        let items: Vec<ElementRef> = match item_selector {
+            Some(selector_str) => {
+                let selector = Selector::parse(selector_str)
+                    .map_err(|e| anyhow!("Invalid item selector '{}': {:?}", selector_str, e))?;
+                document.select(&selector).collect()
+            }
+            None => vec![document.root_element()],
+        };
+        
+        let mut records = Vec::new();
+        
+        let has_xpath_fields = compiled_fields.iter().any(|field| matches!(field, CompiledField::XPath(_)));
+        
+        for item in items {
+            let mut record = ScrapedRecord::new();
+            // Converted once per item and shared by all of its XPath fields
+            let item_xml = if has_xpath_fields {
+                match xpath::XmlDocument::from_element(item) {
+                    Ok(item_xml) => Some(item_xml),
+                    Err(e) => {
+                        warn!("Skipping an item that could not be prepared for XPath: {}", e);
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            
+            for (field, compiled) in fields.iter().zip(&compiled_fields) {
+                let value = match compiled {
+                    CompiledField::Css(selector) => item.select(selector)
+                        .next()
+                        .map(|element| extract_element_value(element, &field.data_type))
+                        .unwrap_or_default(),
+                    CompiledField::Regex(regex) => first_regex_match(regex, &item.html())
+                        .unwrap_or_default(),
+                    // Evaluated against the item's markup, so `.//x` and `//x`
+                    // both stay within the item
+                    CompiledField::XPath(expression) => match &item_xml {
+                        Some(item_xml) => item_xml.select_values(expression, &field.data_type)?
+                            .into_iter()
+                            .next()
+                            .unwrap_or_default(),
+                        None => String::new(),
+                    },
+                    // Looked up in the whole page's metadata, not the item
+                    CompiledField::Metadata(key) => metadata::values_for(&page_metadata, key)
+                        .into_iter()
+                        .next()
+                        .unwrap_or_default(),
+                };
+                record.insert(field.name.clone(), value);
+            }
+            
+            // Skip containers where none of the fields matched anything
+            if record.values().any(|value| !value.is_empty()) {
+                records.push(record);
+            }
+        }
+        
+        if records.is_empty() {
+            warn!("No records found for {} fields", fields.len());
+        } else {
+            info!("Found {} records", records.len());
+        }
+        
+        Ok(records)
+    }
+    
+    /// Records from a JSON response. For JSON jobs the item selector is a
+    /// JSONPath expression too, and every field is evaluated relative to
+    /// each matched item, e.g. `$.price`.
+    fn scrape_json_records(&self, body: &str, item_selector: Option<&str>, fields: &[FieldSpec]) -> Result<Vec<ScrapedRecord>> {
+        let document = jsonpath::parse_json(body)?;
+        
+        let compiled_fields = fields.iter()
+            .map(|field| match field.selector_type {
+                SelectorType::JsonPath => jsonpath::parse_jsonpath(&field.selector)
+                    .map_err(|e| anyhow!("Invalid JSONPath expression for field '{}': {}", field.name, e)),
+                _ => Err(anyhow!("Field '{}' must use a JSONPath selector in a JSON job", field.name)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        let items = match item_selector {
+            Some(expression) => jsonpath::query(&jsonpath::parse_jsonpath(expression)?, &document),
+            None => vec![&document],
+        };
+        
+        let records: Vec<ScrapedRecord> = items
+            .into_iter()
+            .map(|item| {
+                fields.iter()
+                    .zip(&compiled_fields)
+                    .map(|(field, path)| {
+                        let value = jsonpath::query(path, item)
+                            .first()
+                            .map(|value| jsonpath::value_to_string(value))
+                            .unwrap_or_default();
+                        (field.name.clone(), value)
+                    })
+                    .collect::<ScrapedRecord>()
+            })
+            .filter(|record| record.values().any(|value| !value.is_empty()))
+            .collect();
+        
+        if records.is_empty() {
+            warn!("No records found for {} fields", fields.len());
+        } else {
+            info!("Found {} records", records.len());
+        }
+        
+        Ok(records)
+    }
+    
+    /// Scrapes the job without saving anything. `session` is the job's
+    /// stored cookie jar; cookies set during the test are dropped with it.
+    pub async fn test_scrape(&self, job: &ScrapingJob, mut session: CookieJar) -> Result<TestScrapePreview> {
         info!("Testing scrape for job: {}", job.name);
         
-        let results = self.scrape_job(job).await?;
+        let raw = self.scrape_untransformed(job, &mut session).await?;
+        let mut transformed = raw.clone();
+        transforms::apply_to_data(job, &mut transformed)?;
         
         // Limit test results to first 5 items to avoid overwhelming the UI
-        let limited_results = results.into_iter().take(5).collect();
-        
-        Ok(limited_results)
+        Ok(TestScrapePreview {
+            before: raw.item_values().into_iter().take(5).collect(),
+            after: transformed.item_values().into_iter().take(5).collect(),
+        })
     }
     
     pub async fn validate_url(&self, url: &str) -> Result<bool> {
-        let response = self.client.head(url).send().await
+        let response = self.client_for(&ClientKey::default())?.head(url).send().await
             .map_err(|e| anyhow!("Failed to validate URL: {}", e))?;
         
         Ok(response.status().is_success())
@@ -149,6 +941,16 @@ impl WebScraper {
         }
     }
     
+    pub fn validate_xpath(&self, expression: &str) -> Result<bool> {
+        xpath::validate_xpath(expression)?;
+        Ok(true)
+    }
+    
+    pub fn validate_jsonpath(&self, expression: &str) -> Result<bool> {
+        jsonpath::parse_jsonpath(expression)?;
+        Ok(true)
+    }
+    
     pub fn validate_regex_pattern(&self, pattern: &str) -> Result<bool> {
         match Regex::new(pattern) {
             Ok(_) => Ok(true),
@@ -157,6 +959,378 @@ impl WebScraper {
     }
 }
 
+enum CompiledField {
+    Css(Selector),
+    Regex(Regex),
+    XPath(xpath::XPathExpression),
+    Metadata(String),
+}
+
+impl CompiledField {
+    fn compile(field: &FieldSpec) -> Result<Self> {
+        match field.selector_type {
+            SelectorType::CSS => Selector::parse(&field.selector)
+                .map(CompiledField::Css)
+                .map_err(|e| anyhow!("Invalid CSS selector for field '{}': {:?}", field.name, e)),
+            SelectorType::Regex => Regex::new(&field.selector)
+                .map(CompiledField::Regex)
+                .map_err(|e| anyhow!("Invalid regex pattern for field '{}': {}", field.name, e)),
+            SelectorType::Metadata => Ok(CompiledField::Metadata(field.selector.clone())),
+            SelectorType::JsonPath => Err(anyhow!("Field '{}' uses JSONPath, which needs the job's selector type to be JSONPath", field.name)),
+            SelectorType::XPath => xpath::XPathExpression::parse(&field.selector)
+                .map(CompiledField::XPath)
+                .map_err(|e| anyhow!("Invalid XPath expression for field '{}': {}", field.name, e)),
+        }
+    }
+}
+
+/// The job with its secret headers and cookies read back from the
+/// credential store, for saved jobs whose spec holds placeholders.
+fn with_request_secrets(job: &ScrapingJob) -> Result<Cow<'_, ScrapingJob>> {
+    let job_id = match job.id {
+        Some(job_id) if job.request.has_placeholders() => job_id,
+        _ => return Ok(Cow::Borrowed(job)),
+    };
+    let mut job = job.clone();
+    job.request.restore_secrets(&credentials::get_job_request_secrets(job_id)?);
+    Ok(Cow::Owned(job))
+}
+
+/// Every request a job makes goes through here so the job's user agent and
+/// request spec apply the same way whichever client it uses.
+fn job_request(client: &Client, job: &ScrapingJob, url: &str, session: &CookieJar) -> Result<reqwest::RequestBuilder> {
+    let spec = &job.request;
+    let mut request = client.request(job_method(job), url);
+    request = with_job_headers(request, job, url, true, session)?;
+    
+    if !spec.query.is_empty() {
+        request = request.query(&spec.query);
+    }
+    
+    Ok(with_job_body(request, job))
+}
+
+/// The request for the next hop of a redirect. The query is already part of
+/// `url`; the job's own headers and cookies are only sent while the redirect
+/// stays on the job's origin.
+fn redirect_request(
+    client: &Client,
+    job: &ScrapingJob,
+    url: &reqwest::Url,
+    keep_method: bool,
+    same_origin: bool,
+    session: &CookieJar,
+) -> Result<reqwest::RequestBuilder> {
+    let method = if keep_method { job_method(job) } else { reqwest::Method::GET };
+    let request = with_job_headers(client.request(method, url.clone()), job, url.as_str(), same_origin, session)?;
+    Ok(if keep_method { with_job_body(request, job) } else { request })
+}
+
+fn job_method(job: &ScrapingJob) -> reqwest::Method {
+    match job.request.method {
+        HttpMethod::Get => reqwest::Method::GET,
+        HttpMethod::Post => reqwest::Method::POST,
+        HttpMethod::Put => reqwest::Method::PUT,
+        HttpMethod::Patch => reqwest::Method::PATCH,
+        HttpMethod::Delete => reqwest::Method::DELETE,
+    }
+}
+
+fn with_job_headers(
+    mut request: reqwest::RequestBuilder,
+    job: &ScrapingJob,
+    url: &str,
+    include_job_headers: bool,
+    session: &CookieJar,
+) -> Result<reqwest::RequestBuilder> {
+    use reqwest::header::{HeaderName, HeaderValue, COOKIE, USER_AGENT};
+    
+    let spec = &job.request;
+    if let Some(user_agent) = &job.user_agent {
+        request = request.header(USER_AGENT, user_agent);
+    }
+    
+    let mut cookies = Vec::new();
+    if include_job_headers {
+        for (name, value) in &spec.headers {
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|e| anyhow!("Invalid header name '{}': {}", name, e))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| anyhow!("Invalid value for header '{}': {}", name, e))?;
+            request = request.header(name, value);
+        }
+        cookies.extend(spec.cookies.iter().map(|(name, value)| format!("{}={}", name, value)));
+    }
+    if let Some(session_cookies) = reqwest::Url::parse(url).ok().and_then(|url| session.header_for(&url)) {
+        cookies.push(session_cookies);
+    }
+    if !cookies.is_empty() {
+        let value = HeaderValue::from_str(&cookies.join("; "))
+            .map_err(|e| anyhow!("Invalid cookies: {}", e))?;
+        request = request.header(COOKIE, value);
+    }
+    
+    Ok(request)
+}
+
+fn with_job_body(request: reqwest::RequestBuilder, job: &ScrapingJob) -> reqwest::RequestBuilder {
+    match &job.request.body {
+        Some(RequestBody::Form(fields)) => request.form(fields),
+        Some(RequestBody::Json(value)) => request.json(value),
+        Some(RequestBody::Raw { content_type, content }) => {
+            let request = match content_type {
+                Some(content_type) => request.header(reqwest::header::CONTENT_TYPE, content_type),
+                None => request,
+            };
+            request.body(content.clone())
+        }
+        None => request,
+    }
+}
+
+/// Absolute URLs of the links matched by the crawl selector, in document
+/// order without duplicates. Fragments are dropped and only http(s) links
+/// are kept.
+fn extract_links(html: &str, page_url: &reqwest::Url, crawl: &CrawlSpec) -> Result<Vec<String>> {
+    let selector = Selector::parse(&crawl.link_selector)
+        .map_err(|e| anyhow!("Invalid link selector '{}': {:?}", crawl.link_selector, e))?;
+    let document = Html::parse_document(html);
+    let base = document_base_url(html, page_url);
+    
+    let mut seen = HashSet::new();
+    let mut links = Vec::new();
+    for element in document.select(&selector) {
+        let Some(target) = element.value().attr(&crawl.link_attribute) else {
+            continue;
+        };
+        let Ok(mut url) = base.join(target.trim()) else {
+            continue;
+        };
+        if url.scheme() != "http" && url.scheme() != "https" {
+            continue;
+        }
+        url.set_fragment(None);
+        let url = url.to_string();
+        if seen.insert(url.clone()) {
+            links.push(url);
+        }
+    }
+    
+    if links.is_empty() {
+        warn!("No links found with selector: {}", crawl.link_selector);
+    }
+    Ok(links)
+}
+
+const URL_ATTRIBUTES: &[&str] = &[
+    "href", "src", "action", "formaction", "poster", "cite", "data", "data-src", "data-href",
+];
+
+fn is_url_attribute(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::Attribute(name) if URL_ATTRIBUTES.contains(&name.to_lowercase().as_str()))
+}
+
+/// The URL relative links on the page are resolved against: its
+/// `<base href>` if present, the URL it was served from otherwise.
+fn document_base_url(html: &str, page_url: &reqwest::Url) -> reqwest::Url {
+    let selector = Selector::parse("base[href]").unwrap();
+    Html::parse_document(html)
+        .select(&selector)
+        .next()
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| page_url.join(href.trim()).ok())
+        .unwrap_or_else(|| page_url.clone())
+}
+
+fn apply_url_options(options: &UrlOptions, base: &reqwest::Url, page_url: &reqwest::Url, values: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    values
+        .into_iter()
+        .filter_map(|value| apply_url_option(options, base, page_url, value))
+        .filter(|value| !options.dedupe || seen.insert(value.clone()))
+        .collect()
+}
+
+// Resolves one value and checks its host. `None` means the value is dropped.
+fn apply_url_option(options: &UrlOptions, base: &reqwest::Url, page_url: &reqwest::Url, value: String) -> Option<String> {
+    if value.trim().is_empty() {
+        return Some(value);
+    }
+    let resolved = base.join(value.trim()).ok();
+    if options.same_host_only {
+        match &resolved {
+            Some(url) if url.host_str() == page_url.host_str() => {}
+            Some(_) => return None,
+            None => {
+                warn!("Dropping '{}': not a URL, so its host can't be checked", value);
+                return None;
+            }
+        }
+    }
+    match resolved {
+        Some(url) if options.resolve => Some(url.to_string()),
+        _ => Some(value),
+    }
+}
+
+// A session is considered expired when the site answers 401/403, redirects
+// to the login page, or shows the logged-out marker
+fn session_expired(login: &LoginSpec, page: &Result<FetchedPage>) -> bool {
+    match page {
+        Ok(page) => {
+            let on_login_page = reqwest::Url::parse(&login.url).is_ok_and(|login_url| {
+                login_url.host_str() == page.url.host_str() && login_url.path() == page.url.path()
+            });
+            on_login_page
+                || login.logged_out_marker.as_ref().is_some_and(|marker| page.body.contains(marker.as_str()))
+        }
+        Err(e) => e.downcast_ref::<HttpError>().is_some_and(|e| matches!(e.status, 401 | 403)),
+    }
+}
+
+/// Parses a `Retry-After` value given either as seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+fn extract_element_value(element: ElementRef, data_type: &DataType) -> String {
+    match data_type {
+        DataType::Text => {
+            element.text().collect::<Vec<_>>().join(" ").trim().to_string()
+        }
+        DataType::Attribute(attr_name) => {
+            element.value().attr(attr_name)
+                .unwrap_or_default()
+                .to_string()
+        }
+        DataType::InnerHtml => element.inner_html().trim().to_string(),
+        DataType::OuterHtml => element.html(),
+        DataType::OwnText => own_text(&element),
+        DataType::CleanText => visible_text(&element),
+    }
+}
+
+/// A child node as far as text extraction is concerned.
+pub(crate) enum TextChild<'a, E> {
+    Text(&'a str),
+    Element(E),
+}
+
+/// What text extraction needs from an element, so that the HTML tree used
+/// for CSS selectors and the XML copy used for XPath share the walkers below.
+pub(crate) trait TextElement<'a>: Sized {
+    fn tag_name(&self) -> &'a str;
+    fn text_children(&self) -> Vec<TextChild<'a, Self>>;
+}
+
+impl<'a> TextElement<'a> for ElementRef<'a> {
+    fn tag_name(&self) -> &'a str {
+        self.value().name()
+    }
+    
+    fn text_children(&self) -> Vec<TextChild<'a, Self>> {
+        self.children()
+            .filter_map(|child| match child.value().as_text() {
+                Some(text) => Some(TextChild::Text(text)),
+                None => ElementRef::wrap(child).map(TextChild::Element),
+            })
+            .collect()
+    }
+}
+
+/// Text directly inside the element, without its descendants' text.
+pub(crate) fn own_text<'a, E: TextElement<'a>>(element: &E) -> String {
+    let own_text: Vec<&str> = element.text_children()
+        .into_iter()
+        .filter_map(|child| match child {
+            TextChild::Text(text) => Some(text),
+            TextChild::Element(_) => None,
+        })
+        .collect();
+    collapse_whitespace(&own_text.join(" "))
+}
+
+/// All text of the element except scripts and styles.
+pub(crate) fn visible_text<'a, E: TextElement<'a>>(element: &E) -> String {
+    let mut text = String::new();
+    push_visible_text(element, &mut text);
+    collapse_whitespace(&text)
+}
+
+fn push_visible_text<'a, E: TextElement<'a>>(element: &E, text: &mut String) {
+    for child in element.text_children() {
+        match child {
+            TextChild::Text(child_text) => {
+                text.push_str(child_text);
+                text.push(' ');
+            }
+            TextChild::Element(child_element) => {
+                if !matches!(child_element.tag_name(), "script" | "style") {
+                    push_visible_text(&child_element, text);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// If there are capture groups, use the first one; otherwise use the full match
+fn captured_text<'t>(captures: &regex::Captures<'t>) -> &'t str {
+    captured_match(captures).map(|m| m.as_str()).unwrap_or_default()
+}
+
+fn captured_match<'t>(captures: &regex::Captures<'t>) -> Option<regex::Match<'t>> {
+    if captures.len() > 1 {
+        captures.get(1)
+    } else {
+        captures.get(0)
+    }
+}
+
+/// A CSS selector path from the root to `element`, like
+/// `html > body > ul > li:nth-of-type(2)`. It stops at the nearest element
+/// with an `id`.
+pub(crate) fn css_path(element: ElementRef) -> String {
+    let mut steps = Vec::new();
+    let mut current = Some(element);
+    while let Some(element) = current {
+        let value = element.value();
+        if let Some(id) = value.id() {
+            steps.push(format!("{}#{}", value.name(), id));
+            break;
+        }
+        
+        let siblings: Vec<ElementRef> = element.parent()
+            .map(|parent| parent.children().filter_map(ElementRef::wrap).filter(|sibling| sibling.value().name() == value.name()).collect())
+            .unwrap_or_default();
+        if siblings.len() > 1 {
+            let position = siblings.iter().position(|sibling| sibling.id() == element.id()).unwrap_or(0) + 1;
+            steps.push(format!("{}:nth-of-type({})", value.name(), position));
+        } else {
+            steps.push(value.name().to_string());
+        }
+        current = element.parent().and_then(ElementRef::wrap);
+    }
+    steps.reverse();
+    steps.join(" > ")
+}
+
+fn first_regex_match(regex: &Regex, text: &str) -> Option<String> {
+    regex.captures_iter(text)
+        .map(|captures| captured_text(&captures).to_string())
+        .find(|matched| !matched.is_empty())
+}
+
 impl Default for WebScraper {
     fn default() -> Self {
         Self::new()
@@ -166,6 +1340,7 @@ impl Default for WebScraper {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
     
     #[test]
     fn test_css_selector_validation() {
@@ -219,6 +1394,24 @@ mod tests {
         assert_eq!(link_results[0], "https://example.com");
     }
     
+    #[test]
+    fn test_element_data_types() {
+        let html = r#"<div id="item">  Price:
+            <b>$10</b> <script>track()</script><style>b { color: red }</style> each </div>"#;
+        let document = Html::parse_document(html);
+        let element = document.select(&Selector::parse("#item").unwrap()).next().unwrap();
+        
+        assert_eq!(extract_element_value(element, &DataType::OwnText), "Price: each");
+        assert_eq!(extract_element_value(element, &DataType::CleanText), "Price: $10 each");
+        assert!(extract_element_value(element, &DataType::InnerHtml).starts_with("Price:"));
+        assert!(extract_element_value(element, &DataType::OuterHtml).starts_with(r#"<div id="item">"#));
+        
+        for data_type in [DataType::InnerHtml, DataType::OuterHtml, DataType::OwnText, DataType::CleanText] {
+            let parsed: DataType = data_type.to_string().parse().unwrap();
+            assert_eq!(parsed.to_string(), data_type.to_string());
+        }
+    }
+    
     #[test]
     fn test_regex_scraping() {
         let scraper = WebScraper::new();
@@ -235,4 +1428,407 @@ mod tests {
         assert_eq!(email_results[0], "john@example.com");
         assert_eq!(email_results[1], "jane@test.org");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_field_scraping() {
+        let scraper = WebScraper::new();
+        let html = r#"
+            <ul>
+                <li class="product"><h2>Widget</h2><span class="price">$10</span><a href="/w">more</a></li>
+                <li class="product"><h2>Gadget</h2><span class="price">$25</span><a href="/g">more</a></li>
+                <li class="ad">Sponsored</li>
+            </ul>
+        "#;
+        
+        let fields = vec![
+            FieldSpec {
+                name: "title".to_string(),
+                selector_type: SelectorType::CSS,
+                selector: "h2".to_string(),
+                data_type: DataType::Text,
+                transforms: Vec::new(),
+            },
+            FieldSpec {
+                name: "price".to_string(),
+                selector_type: SelectorType::Regex,
+                selector: r"\$(\d+)".to_string(),
+                data_type: DataType::Text,
+                transforms: Vec::new(),
+            },
+            FieldSpec {
+                name: "url".to_string(),
+                selector_type: SelectorType::CSS,
+                selector: "a".to_string(),
+                data_type: DataType::Attribute("href".to_string()),
+                transforms: Vec::new(),
+            },
+        ];
+        
+        let records = scraper.scrape_records(html, Some("li.product"), &fields).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["title"], "Widget");
+        assert_eq!(records[0]["price"], "10");
+        assert_eq!(records[0]["url"], "/w");
+        assert_eq!(records[1]["title"], "Gadget");
+        
+        // Without an item selector the whole document is a single record
+        let single = scraper.scrape_records(html, None, &fields[..1]).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0]["title"], "Widget");
+    }
+    
+    #[test]
+    fn test_json_records() {
+        let scraper = WebScraper::new();
+        let body = r#"{"results": [
+            {"title": "Widget", "price": {"amount": 10}},
+            {"title": "Gadget", "price": {"amount": 25.5}},
+            {"other": true}
+        ]}"#;
+        let fields = vec![
+            FieldSpec {
+                name: "title".to_string(),
+                selector_type: SelectorType::JsonPath,
+                selector: "$.title".to_string(),
+                data_type: DataType::Text,
+                transforms: Vec::new(),
+            },
+            FieldSpec {
+                name: "price".to_string(),
+                selector_type: SelectorType::JsonPath,
+                selector: "$.price.amount".to_string(),
+                data_type: DataType::Text,
+                transforms: Vec::new(),
+            },
+        ];
+        
+        let records = scraper.scrape_json_records(body, Some("$.results[*]"), &fields).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["title"], "Widget");
+        assert_eq!(records[1]["price"], "25.5");
+        
+        let css_field = FieldSpec { selector_type: SelectorType::CSS, ..fields[0].clone() };
+        assert!(scraper.scrape_json_records(body, None, &[css_field]).is_err());
+    }
+    
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        // Dates in the past mean retry immediately
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+    
+    fn sample_job() -> ScrapingJob {
+        ScrapingJob {
+            name: "Products".to_string(),
+            url: "https://example.com/products".to_string(),
+            selector: "li".to_string(),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_select_matches() {
+        let scraper = WebScraper::new();
+        let html = r#"<html><body>
+            <ul><li>Widget</li><li></li></ul>
+            <div id="main"><p>Price: $10</p></div>
+        </body></html>"#;
+        
+        let matches = scraper.select_matches(html, &SelectorType::CSS, "li", &DataType::Text).unwrap();
+        assert_eq!(matches, vec![
+            SelectorMatch { path: "html > body > ul > li:nth-of-type(1)".to_string(), value: "Widget".to_string() },
+            SelectorMatch { path: "html > body > ul > li:nth-of-type(2)".to_string(), value: String::new() },
+        ]);
+        
+        let matches = scraper.select_matches(html, &SelectorType::CSS, "p", &DataType::Text).unwrap();
+        assert_eq!(matches[0].path, "div#main > p");
+        
+        let matches = scraper.select_matches(html, &SelectorType::Regex, r"\$(\d+)", &DataType::Text).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].value, "10");
+        assert_eq!(matches[0].path, format!("offset {}", html.find("10").unwrap()));
+    }
+    
+    #[test]
+    fn test_reextract_snapshots() {
+        let scraper = WebScraper::new();
+        let page = |kind, url: &str, body: &str| PageSnapshot {
+            kind,
+            url: url.to_string(),
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_string(),
+        };
+        let snapshots = vec![
+            page(SnapshotKind::Page, "https://example.com/products", r#"<li><a href="/w">Widget</a></li>"#),
+            page(SnapshotKind::Page, "https://example.com/products?page=2", r#"<li><a href="/g">Gadget</a></li>"#),
+        ];
+        
+        // The selector changed since the run; the stored pages still hold the data
+        let job = ScrapingJob {
+            selector: "li a".to_string(),
+            data_type: DataType::Attribute("href".to_string()),
+            url_options: UrlOptions { resolve: true, ..Default::default() },
+            transforms: vec![Transform::Lowercase],
+            ..sample_job()
+        };
+        let data = scraper.reextract(&job, &snapshots).unwrap();
+        assert_eq!(data.values, vec!["https://example.com/w", "https://example.com/g"]);
+        assert_eq!(data.http_status, Some(200));
+        
+        // Crawling jobs only extract their detail pages
+        let crawling = ScrapingJob { crawl: Some(CrawlSpec::default()), ..job };
+        assert!(scraper.reextract(&crawling, &snapshots).unwrap().is_empty());
+        
+        // The status is the job page's, whatever order the pages come in
+        let mut detail = page(SnapshotKind::Detail, "https://example.com/w", "<h1>Widget</h1>");
+        detail.status = 404;
+        let snapshots = vec![detail, snapshots[0].clone()];
+        assert_eq!(scraper.reextract(&crawling, &snapshots).unwrap().http_status, Some(200));
+    }
+    
+    #[test]
+    fn test_job_request() {
+        let client = Client::new();
+        let mut job = ScrapingJob {
+            name: "Search".to_string(),
+            url: "https://example.com/search?lang=en".to_string(),
+            user_agent: Some("TestBot/1.0".to_string()),
+            request: RequestSpec {
+                method: HttpMethod::Post,
+                headers: BTreeMap::from([("Accept-Language".to_string(), "de".to_string())]),
+                cookies: BTreeMap::from([
+                    ("session".to_string(), "abc".to_string()),
+                    ("theme".to_string(), "dark".to_string()),
+                ]),
+                query: BTreeMap::from([("q".to_string(), "rust lang".to_string())]),
+                body: Some(RequestBody::Form(BTreeMap::from([("page".to_string(), "2".to_string())]))),
+            },
+            ..sample_job()
+        };
+        
+        let request = job_request(&client, &job, &job.url, &CookieJar::default()).unwrap().build().unwrap();
+        assert_eq!(request.method(), &reqwest::Method::POST);
+        assert_eq!(request.url().as_str(), "https://example.com/search?lang=en&q=rust+lang");
+        assert_eq!(request.headers()["user-agent"], "TestBot/1.0");
+        assert_eq!(request.headers()["accept-language"], "de");
+        assert_eq!(request.headers()["cookie"], "session=abc; theme=dark");
+        assert_eq!(request.headers()["content-type"], "application/x-www-form-urlencoded");
+        assert_eq!(request.body().and_then(|body| body.as_bytes()), Some(&b"page=2"[..]));
+        
+        // A 303 to another site turns into a plain GET without the job's
+        // own headers, but still carries that site's session cookies
+        let mut session = CookieJar::default();
+        let landing = reqwest::Url::parse("https://accounts.example.org/landing").unwrap();
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::SET_COOKIE, "sid=xyz; Path=/".parse().unwrap());
+        session.store_response_cookies(&landing, &headers);
+        let redirect = redirect_request(&client, &job, &landing, false, false, &session).unwrap().build().unwrap();
+        assert_eq!(redirect.method(), &reqwest::Method::GET);
+        assert_eq!(redirect.headers()["cookie"], "sid=xyz");
+        assert!(redirect.headers().get("accept-language").is_none());
+        assert!(redirect.body().is_none());
+        
+        let same_site = reqwest::Url::parse("https://example.com/search/v2?lang=en").unwrap();
+        let repeated = redirect_request(&client, &job, &same_site, true, true, &session).unwrap().build().unwrap();
+        assert_eq!(repeated.method(), &reqwest::Method::POST);
+        assert_eq!(repeated.url(), &same_site);
+        assert_eq!(repeated.headers()["cookie"], "session=abc; theme=dark");
+        assert_eq!(repeated.body().and_then(|body| body.as_bytes()), Some(&b"page=2"[..]));
+        
+        job.request.headers.insert("Bad Header".to_string(), "x".to_string());
+        assert!(job_request(&client, &job, &job.url, &CookieJar::default()).is_err());
+    }
+    
+    #[test]
+    fn test_extract_links() {
+        let html = r#"
+            <ul>
+                <li><a class="item" href="/products/1">One</a></li>
+                <li><a class="item" href="2#reviews">Two</a></li>
+                <li><a class="item" href="https://cdn.example.org/3">Three</a></li>
+                <li><a class="item" href="/products/1">One again</a></li>
+                <li><a class="item" href="mailto:sales@example.com">Mail</a></li>
+                <li><a class="item">No link</a></li>
+            </ul>
+        "#;
+        let page_url = reqwest::Url::parse("https://example.com/products/?page=1").unwrap();
+        let crawl = CrawlSpec { link_selector: "a.item".to_string(), ..Default::default() };
+        
+        assert_eq!(extract_links(html, &page_url, &crawl).unwrap(), vec![
+            "https://example.com/products/1",
+            "https://example.com/products/2",
+            "https://cdn.example.org/3",
+        ]);
+    }
+    
+    #[test]
+    fn test_url_options() {
+        let page_url = reqwest::Url::parse("https://example.com/shop/list?page=2").unwrap();
+        let html = r#"<html><head><base href="/catalog/"></head><body></body></html>"#;
+        let base = document_base_url(html, &page_url);
+        assert_eq!(base.as_str(), "https://example.com/catalog/");
+        assert_eq!(document_base_url("<p>no base</p>", &page_url), page_url);
+        
+        let values = vec![
+            "item/42".to_string(),
+            "/item/42".to_string(),
+            "https://example.com/catalog/item/42".to_string(),
+            "https://other.com/x".to_string(),
+        ];
+        let resolve_only = UrlOptions { resolve: true, ..Default::default() };
+        assert_eq!(apply_url_options(&resolve_only, &base, &page_url, values.clone()), vec![
+            "https://example.com/catalog/item/42",
+            "https://example.com/item/42",
+            "https://example.com/catalog/item/42",
+            "https://other.com/x",
+        ]);
+        
+        let all = UrlOptions { resolve: true, dedupe: true, same_host_only: true };
+        assert_eq!(apply_url_options(&all, &base, &page_url, values), vec![
+            "https://example.com/catalog/item/42",
+            "https://example.com/item/42",
+        ]);
+        
+        // A value that isn't a URL is kept as it is, unless its host has to
+        // be checked
+        let unresolvable = vec!["http://[::1".to_string()];
+        assert_eq!(apply_url_options(&resolve_only, &base, &page_url, unresolvable.clone()), vec!["http://[::1"]);
+        assert!(apply_url_options(&all, &base, &page_url, unresolvable).is_empty());
+        
+        assert!(is_url_attribute(&DataType::Attribute("HREF".to_string())));
+        assert!(!is_url_attribute(&DataType::Attribute("data-sku".to_string())));
+    }
+    
+    #[test]
+    fn test_url_options_for_records() {
+        let scraper = WebScraper::new();
+        let html = r#"<ul>
+            <li><a href="/item/1">One</a></li>
+            <li><a href="https://example.com/item/1">One again</a></li>
+            <li><a href="https://other.com/item/2">Elsewhere</a></li>
+            <li><span>No link</span></li>
+        </ul>"#;
+        let page = FetchedPage {
+            status: 200,
+            url: reqwest::Url::parse("https://example.com/list").unwrap(),
+            headers: Vec::new(),
+            body: html.to_string(),
+        };
+        let field = |name: &str, data_type: DataType| FieldSpec {
+            name: name.to_string(),
+            selector_type: SelectorType::CSS,
+            selector: "a, span".to_string(),
+            data_type,
+            transforms: Vec::new(),
+        };
+        let job = ScrapingJob {
+            item_selector: Some("li".to_string()),
+            fields: vec![field("name", DataType::Text), field("link", DataType::Attribute("href".to_string()))],
+            url_options: UrlOptions { resolve: true, dedupe: true, same_host_only: true },
+            ..sample_job()
+        };
+        
+        let data = scraper.extract_from(&job, &page).unwrap();
+        let links: Vec<&str> = data.records.iter().map(|record| record["link"].as_str()).collect();
+        assert_eq!(links, vec!["https://example.com/item/1", "", ""]);
+        assert_eq!(data.records[1]["name"], "Elsewhere");
+    }
+    
+    #[test]
+    fn test_clients_are_reused_per_proxy() {
+        let scraper = WebScraper::new();
+        let proxied = ClientKey {
+            proxy_url: Some("http://proxy.local:8080".to_string()),
+            accept_invalid_certs: false,
+            manual_redirects: false,
+        };
+        
+        scraper.client_for(&ClientKey::default()).unwrap();
+        scraper.client_for(&proxied).unwrap();
+        scraper.client_for(&proxied.clone()).unwrap();
+        assert_eq!(scraper.clients.lock().unwrap().len(), 2);
+        
+        let invalid = ClientKey { proxy_url: Some("::".to_string()), ..Default::default() };
+        assert!(scraper.client_for(&invalid).is_err());
+        assert_eq!(scraper.clients.lock().unwrap().len(), 2);
+    }
+    
+    /// Serves one response per connection from `respond`, which gets the
+    /// request target and returns the status and body. Returns the base URL.
+    async fn serve(respond: fn(&str) -> (u16, &'static str)) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = vec![0; 8192];
+                let read = stream.read(&mut buffer).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buffer[..read]);
+                let target = request.split_whitespace().nth(1).unwrap_or("/");
+                let (status, body) = respond(target);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body,
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}", address)
+    }
+    
+    #[tokio::test]
+    async fn test_pagination_keeps_pages_before_a_failure() {
+        let base = serve(|target| match target {
+            "/products?page=1" => (200, "<ul><li>A</li><li>B</li></ul>"),
+            "/products?page=2" => (500, "oops"),
+            _ => (200, "<ul><li>C</li></ul>"),
+        }).await;
+        
+        let scraper = WebScraper::new();
+        scraper.politeness().update_settings(ScraperSettings {
+            min_host_delay_ms: 0,
+            respect_robots_txt: false,
+            ..Default::default()
+        });
+        let job = ScrapingJob {
+            url: format!("{}/products", base),
+            pagination: Some(PaginationSpec::default()),
+            ..sample_job()
+        };
+        
+        let data = scraper.scrape_job_with_session(&job, &mut CookieJar::default()).await.unwrap();
+        assert_eq!(data.values, vec!["A", "B"]);
+    }
+    
+    #[tokio::test]
+    async fn test_crawl_skips_detail_pages_that_fail_to_extract() {
+        let base = serve(|target| match target {
+            "/products" => (200, r#"<a href="/a">A</a><a href="/b">B</a><a href="/c">C</a>"#),
+            "/b" => (200, "<html>not JSON</html>"),
+            "/a" => (200, r#"{"name": "Widget"}"#),
+            _ => (200, r#"{"name": "Gadget"}"#),
+        }).await;
+        
+        let scraper = WebScraper::new();
+        scraper.politeness().update_settings(ScraperSettings {
+            min_host_delay_ms: 0,
+            respect_robots_txt: false,
+            ..Default::default()
+        });
+        let job = ScrapingJob {
+            url: format!("{}/products", base),
+            selector_type: SelectorType::JsonPath,
+            selector: "$.name".to_string(),
+            crawl: Some(CrawlSpec { link_selector: "a".to_string(), ..Default::default() }),
+            ..sample_job()
+        };
+        
+        let data = scraper.scrape_job_with_session(&job, &mut CookieJar::default()).await.unwrap();
+        assert_eq!(data.values, vec!["Widget", "Gadget"]);
+    }
+}
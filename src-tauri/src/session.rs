@@ -0,0 +1,214 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// A cookie kept between runs of a job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    /// Set when the server gave no `Domain` attribute, so the cookie is only
+    /// sent back to the exact host that set it.
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    /// `None` for session cookies, which are kept until the jar is cleared.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// The cookies of one job's logged-in session, persisted in SQLite.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CookieJar {
+    pub cookies: Vec<StoredCookie>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_lowercase(),
+            None => return false,
+        };
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            host == self.domain || host.ends_with(&format!(".{}", self.domain))
+        };
+        domain_matches
+            && path_matches(&self.path, url.path())
+            && (!self.secure || url.scheme() == "https")
+    }
+}
+
+impl CookieJar {
+    pub fn has_cookies(&self) -> bool {
+        let now = Utc::now();
+        self.cookies.iter().any(|cookie| !cookie.is_expired(now))
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// Records the `Set-Cookie` headers of a response to `url`.
+    pub fn store_response_cookies(&mut self, url: &Url, headers: &HeaderMap) {
+        let now = Utc::now();
+        for value in headers.get_all(SET_COOKIE) {
+            let Some(cookie) = value.to_str().ok().and_then(|value| parse_set_cookie(value, url, now)) else {
+                continue;
+            };
+            self.cookies.retain(|existing| {
+                !(existing.name == cookie.name && existing.domain == cookie.domain && existing.path == cookie.path)
+            });
+            // An expiry in the past is how servers delete a cookie
+            if !cookie.is_expired(now) {
+                self.cookies.push(cookie);
+            }
+        }
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+    }
+
+    /// The `Cookie` header value to send with a request to `url`.
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        let now = Utc::now();
+        let pairs: Vec<String> = self.cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        if pairs.is_empty() {
+            None
+        } else {
+            Some(pairs.join("; "))
+        }
+    }
+}
+
+fn parse_set_cookie(header: &str, url: &Url, now: DateTime<Utc>) -> Option<StoredCookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let host = url.host_str()?.to_lowercase();
+    let mut cookie = StoredCookie {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(url.path()),
+        secure: false,
+        expires_at: None,
+    };
+    let mut max_age = None;
+
+    for attribute in parts {
+        let (key, value) = match attribute.split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => (attribute.trim().to_lowercase(), ""),
+        };
+        match key.as_str() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_lowercase();
+                // Servers may not set cookies for unrelated domains
+                if host != domain && !host.ends_with(&format!(".{}", domain)) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "expires" => {
+                cookie.expires_at = DateTime::parse_from_rfc2822(value)
+                    .ok()
+                    .map(|date| date.with_timezone(&Utc));
+            }
+            _ => {}
+        }
+    }
+
+    // Max-Age takes precedence over Expires
+    if let Some(seconds) = max_age {
+        cookie.expires_at = Some(now + ChronoDuration::seconds(seconds.max(0)));
+    }
+
+    Some(cookie)
+}
+
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => request_path[..index].to_string(),
+    }
+}
+
+fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(SET_COOKIE, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_store_and_send_cookies() {
+        let login_url = Url::parse("https://shop.example.com/account/login").unwrap();
+        let mut jar = CookieJar::default();
+        jar.store_response_cookies(&login_url, &headers(&[
+            "session=abc123; Path=/; Secure; HttpOnly",
+            "prefs=compact; Domain=.example.com; Path=/; Max-Age=3600",
+            "tracking=1; Domain=other.com",
+        ]));
+        assert_eq!(jar.cookies.len(), 2);
+        assert!(jar.has_cookies());
+
+        let page = Url::parse("https://shop.example.com/products").unwrap();
+        assert_eq!(jar.header_for(&page).as_deref(), Some("session=abc123; prefs=compact"));
+
+        // Host-only and secure cookies stay on their host and on https
+        let other_host = Url::parse("http://www.example.com/").unwrap();
+        assert_eq!(jar.header_for(&other_host).as_deref(), Some("prefs=compact"));
+    }
+
+    #[test]
+    fn test_expired_cookies_are_removed() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut jar = CookieJar::default();
+        jar.store_response_cookies(&url, &headers(&["session=abc"]));
+        assert!(jar.has_cookies());
+
+        jar.store_response_cookies(&url, &headers(&["session=; Expires=Thu, 01 Jan 1970 00:00:00 GMT"]));
+        assert!(!jar.has_cookies());
+        assert_eq!(jar.header_for(&url), None);
+    }
+
+    #[test]
+    fn test_path_matching() {
+        assert!(path_matches("/", "/anything"));
+        assert!(path_matches("/account", "/account"));
+        assert!(path_matches("/account", "/account/orders"));
+        assert!(!path_matches("/account", "/accounting"));
+        assert_eq!(default_path("/account/login"), "/account");
+        assert_eq!(default_path("/login"), "/");
+    }
+}
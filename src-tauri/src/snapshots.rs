@@ -0,0 +1,42 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use anyhow::Result;
+
+// Response bodies of runs with snapshots enabled are stored gzip-compressed
+// in `snapshot_bodies`, keyed by the SHA-256 of the uncompressed body. Runs
+// of a page that didn't change share one stored body.
+
+pub fn body_hash(body: &str) -> String {
+    format!("{:x}", Sha256::digest(body.as_bytes()))
+}
+
+pub fn compress(body: &str) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decompress(data: &[u8]) -> Result<String> {
+    let mut body = String::new();
+    GzDecoder::new(data).read_to_string(&mut body)?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_round_trip() {
+        let body = "<html><body>".to_string() + &"<p>Widget €10</p>".repeat(200) + "</body></html>";
+        let compressed = compress(&body).unwrap();
+        assert!(compressed.len() < body.len() / 10);
+        assert_eq!(decompress(&compressed).unwrap(), body);
+
+        assert_eq!(body_hash(&body), body_hash(&body.clone()));
+        assert_ne!(body_hash(&body), body_hash("<html></html>"));
+    }
+}
@@ -0,0 +1,231 @@
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashSet;
+use crate::models::{SelectorBasis, SelectorSuggestion};
+use crate::scraper::{collapse_whitespace, css_path};
+
+// Selectors are suggested by finding the innermost elements whose text
+// contains the example, proposing selectors for them from the most to the
+// least stable kind, and keeping those that still select the example when
+// run against the whole page.
+
+/// Example elements looked at; more usually means the example is too short.
+const MAX_TARGETS: usize = 5;
+/// Values returned with each suggestion.
+const MAX_PREVIEW_VALUES: usize = 10;
+
+// Attributes that tend to be kept stable for testing or structured data
+const STABLE_ATTRIBUTES: &[&str] = &["itemprop", "data-testid", "data-test", "data-qa", "name", "aria-label"];
+
+pub fn suggest_selectors(html: &str, example: &str, limit: usize) -> Vec<SelectorSuggestion> {
+    let example = collapse_whitespace(example);
+    if example.is_empty() {
+        return Vec::new();
+    }
+
+    let document = Html::parse_document(html);
+    let targets = find_targets(&document, &example);
+
+    let mut seen = HashSet::new();
+    let mut suggestions: Vec<SelectorSuggestion> = targets
+        .iter()
+        .flat_map(|target| candidates(*target))
+        .filter(|(selector, _)| seen.insert(selector.clone()))
+        .filter_map(|(selector, basis)| evaluate(&document, &targets, selector, basis))
+        .collect();
+
+    suggestions.sort_by(|a, b| {
+        stability(b.basis).cmp(&stability(a.basis))
+            .then_with(|| a.selector.len().cmp(&b.selector.len()))
+    });
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// The innermost elements whose text contains `example`.
+fn find_targets<'a>(document: &'a Html, example: &str) -> Vec<ElementRef<'a>> {
+    let all = Selector::parse("body *").unwrap();
+    let containing: Vec<ElementRef> = document
+        .select(&all)
+        .filter(|element| !matches!(element.value().name(), "script" | "style" | "noscript"))
+        .filter(|element| element_text(*element).contains(example))
+        .collect();
+
+    containing
+        .iter()
+        .filter(|element| {
+            !element.children()
+                .filter_map(ElementRef::wrap)
+                .any(|child| containing.iter().any(|other| other.id() == child.id()))
+        })
+        .take(MAX_TARGETS)
+        .copied()
+        .collect()
+}
+
+fn candidates(element: ElementRef) -> Vec<(String, SelectorBasis)> {
+    let value = element.value();
+    let tag = value.name();
+    let classes = stable_classes(element);
+    let mut candidates = Vec::new();
+
+    if let Some(id) = value.id().filter(|id| is_stable_name(id)) {
+        candidates.push((format!("#{}", css_escape(id)), SelectorBasis::Id));
+    }
+
+    for attribute in STABLE_ATTRIBUTES {
+        if let Some(attribute_value) = value.attr(attribute) {
+            candidates.push((
+                format!("{}[{}=\"{}\"]", tag, attribute, attribute_value.replace('"', "\\\"")),
+                SelectorBasis::Attribute,
+            ));
+        }
+    }
+
+    for class in &classes {
+        candidates.push((format!("{}.{}", tag, css_escape(class)), SelectorBasis::Class));
+    }
+    if classes.len() > 1 {
+        candidates.push((
+            format!("{}.{}.{}", tag, css_escape(&classes[0]), css_escape(&classes[1])),
+            SelectorBasis::Class,
+        ));
+    }
+
+    let ancestors: Vec<ElementRef> = element.ancestors().filter_map(ElementRef::wrap).collect();
+    if let Some(ancestor) = ancestors.iter().find(|ancestor| ancestor.value().id().is_some_and(is_stable_name)) {
+        let id = css_escape(ancestor.value().id().unwrap_or_default());
+        let target = match classes.first() {
+            Some(class) => format!("{}.{}", tag, css_escape(class)),
+            None => tag.to_string(),
+        };
+        candidates.push((format!("#{} {}", id, target), SelectorBasis::IdAncestor));
+    }
+    if let Some(parent) = ancestors.first() {
+        if let Some(class) = stable_classes(*parent).first() {
+            candidates.push((
+                format!("{}.{} > {}", parent.value().name(), css_escape(class), tag),
+                SelectorBasis::ParentClass,
+            ));
+        }
+    }
+
+    candidates.push((css_path(element), SelectorBasis::Structure));
+    candidates
+}
+
+/// Runs `selector` on the page; suggestions that miss every target are
+/// dropped.
+fn evaluate(document: &Html, targets: &[ElementRef], selector: String, basis: SelectorBasis) -> Option<SelectorSuggestion> {
+    let parsed = Selector::parse(&selector).ok()?;
+    let matched: Vec<ElementRef> = document.select(&parsed).collect();
+    if !matched.iter().any(|element| targets.iter().any(|target| target.id() == element.id())) {
+        return None;
+    }
+
+    Some(SelectorSuggestion {
+        selector,
+        basis,
+        match_count: matched.len(),
+        matches: matched.iter().take(MAX_PREVIEW_VALUES).map(|element| element_text(*element)).collect(),
+    })
+}
+
+fn stability(basis: SelectorBasis) -> u32 {
+    match basis {
+        SelectorBasis::Id => 100,
+        SelectorBasis::Attribute => 90,
+        SelectorBasis::IdAncestor => 80,
+        SelectorBasis::Class => 70,
+        SelectorBasis::ParentClass => 60,
+        SelectorBasis::Structure => 10,
+    }
+}
+
+fn element_text(element: ElementRef) -> String {
+    collapse_whitespace(&element.text().collect::<Vec<_>>().join(" "))
+}
+
+/// Classes in the order they appear in the `class` attribute; scraper's
+/// `classes()` iterates them sorted, which would favour whichever name
+/// happens to sort first rather than the one the page author put first.
+fn stable_classes(element: ElementRef) -> Vec<String> {
+    element.value()
+        .attr("class")
+        .unwrap_or_default()
+        .split_ascii_whitespace()
+        .filter(|class| is_stable_name(class))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Rejects names that look generated by a build tool or framework, such as
+/// `css-1x2ab3c` or `sc-bdVaJa`, since they change between deployments.
+fn is_stable_name(name: &str) -> bool {
+    const GENERATED_PREFIXES: &[&str] = &["css-", "sc-", "jsx-", "emotion-", "ember", "ng-"];
+    let digits = name.chars().filter(|c| c.is_ascii_digit()).count();
+    !name.is_empty()
+        && name.len() <= 40
+        && digits < 3
+        && !GENERATED_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+fn css_escape(identifier: &str) -> String {
+    let mut escaped = String::new();
+    for (index, c) in identifier.chars().enumerate() {
+        let valid = c.is_alphanumeric() || c == '-' || c == '_';
+        if !valid || (index == 0 && c.is_ascii_digit()) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"
+        <html><body>
+            <div id="results">
+                <div class="product card css-1x2y3z">
+                    <h2 itemprop="name">Widget</h2>
+                    <span class="price">$19.99</span>
+                </div>
+                <div class="product card css-9q8r7s">
+                    <h2 itemprop="name">Gadget</h2>
+                    <span class="price">$5.00</span>
+                </div>
+            </div>
+            <script>var price = "$19.99";</script>
+        </body></html>
+    "#;
+
+    #[test]
+    fn test_suggest_from_example() {
+        let suggestions = suggest_selectors(HTML, "$19.99", 10);
+        let selectors: Vec<&str> = suggestions.iter().map(|s| s.selector.as_str()).collect();
+        assert_eq!(selectors[0], "#results span.price");
+        assert!(selectors.contains(&"span.price"));
+        assert!(selectors.contains(&"div.product > span"));
+        assert!(!selectors.iter().any(|selector| selector.contains("css-")));
+        assert_eq!(suggestions.last().unwrap().basis, SelectorBasis::Structure);
+
+        let price = suggestions.iter().find(|s| s.selector == "span.price").unwrap();
+        assert_eq!(price.match_count, 2);
+        assert_eq!(price.matches, vec!["$19.99", "$5.00"]);
+
+        let name = &suggest_selectors(HTML, "Gadget", 1)[0];
+        assert_eq!(name.selector, "h2[itemprop=\"name\"]");
+        assert_eq!(name.match_count, 2);
+    }
+
+    #[test]
+    fn test_generated_names_are_skipped() {
+        assert!(is_stable_name("price"));
+        assert!(is_stable_name("col-2"));
+        assert!(!is_stable_name("css-1x2y3z"));
+        assert!(!is_stable_name("a8f3e91"));
+        assert!(suggest_selectors(HTML, "not on the page", 5).is_empty());
+    }
+}
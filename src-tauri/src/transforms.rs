@@ -0,0 +1,230 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use std::collections::HashSet;
+use crate::models::{ScrapedData, ScrapingJob, Transform};
+use crate::numbers::parse_number;
+use anyhow::{Result, anyhow};
+use log::warn;
+
+// Transforms work on a list of values so that split, join and dedupe can
+// change how many there are. A single-selector job runs its pipeline over
+// all of its values at once; a record field runs it over its one value.
+//
+// A value that can't be parsed as a number, price or date becomes empty
+// rather than failing the run, so that `DefaultIfEmpty` can fill it in.
+
+/// Joins what is left of a record field when its pipeline produces more
+/// than one value, e.g. after a split without a join.
+const FIELD_VALUE_SEPARATOR: &str = ", ";
+
+/// Applies the job's transforms to freshly extracted data. Records run the
+/// job-wide transforms first, then the field's own ones.
+pub fn apply_to_data(job: &ScrapingJob, data: &mut ScrapedData) -> Result<()> {
+    let job_pipeline = Pipeline::compile(&job.transforms)?;
+    if !job.transforms.is_empty() {
+        data.values = job_pipeline.run(std::mem::take(&mut data.values));
+    }
+
+    for field in &job.fields {
+        if job.transforms.is_empty() && field.transforms.is_empty() {
+            continue;
+        }
+        let field_pipeline = Pipeline::compile(&field.transforms)?;
+        for record in &mut data.records {
+            let Some(value) = record.get_mut(&field.name) else {
+                continue;
+            };
+            let values = job_pipeline.run(vec![std::mem::take(value)]);
+            *value = field_pipeline.run(values).join(FIELD_VALUE_SEPARATOR);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `transforms` over `values` in order.
+pub fn apply(transforms: &[Transform], values: Vec<String>) -> Result<Vec<String>> {
+    Ok(Pipeline::compile(transforms)?.run(values))
+}
+
+/// Checks the parts of a pipeline that can be invalid, such as regex
+/// patterns, without running it.
+pub fn validate(transforms: &[Transform]) -> Result<()> {
+    Pipeline::compile(transforms).map(|_| ())
+}
+
+/// A list of transforms with their regexes compiled, so that running it
+/// over every record of a run compiles each pattern once.
+struct Pipeline<'a> {
+    steps: Vec<(&'a Transform, Option<Regex>)>,
+}
+
+impl<'a> Pipeline<'a> {
+    fn compile(transforms: &'a [Transform]) -> Result<Self> {
+        let steps = transforms
+            .iter()
+            .map(|transform| match transform {
+                Transform::RegexReplace { pattern, .. } => Ok((transform, Some(compile(pattern)?))),
+                _ => Ok((transform, None)),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Pipeline { steps })
+    }
+
+    fn run(&self, mut values: Vec<String>) -> Vec<String> {
+        for (transform, regex) in &self.steps {
+            values = apply_one(transform, regex.as_ref(), values);
+        }
+        values
+    }
+}
+
+// `regex` is the compiled pattern of a `RegexReplace`
+fn apply_one(transform: &Transform, regex: Option<&Regex>, values: Vec<String>) -> Vec<String> {
+    match transform {
+        Transform::Trim => values.into_iter().map(|value| value.trim().to_string()).collect(),
+        Transform::Lowercase => values.into_iter().map(|value| value.to_lowercase()).collect(),
+        Transform::RegexReplace { replacement, .. } => match regex {
+            Some(regex) => values
+                .into_iter()
+                .map(|value| regex.replace_all(&value, replacement.as_str()).into_owned())
+                .collect(),
+            None => values,
+        },
+        Transform::Substring { start, length } => values
+            .into_iter()
+            .map(|value| {
+                let chars = value.chars().skip(*start);
+                match length {
+                    Some(length) => chars.take(*length).collect(),
+                    None => chars.collect(),
+                }
+            })
+            .collect(),
+        Transform::ParseNumber { locale } => values
+            .into_iter()
+            .map(|value| parse_number(&value, locale).map(format_number).unwrap_or_else(|| {
+                warn!("Could not parse '{}' as a number", value);
+                String::new()
+            }))
+            .collect(),
+        Transform::ParseCurrency { locale } => values
+            .into_iter()
+            .map(|value| parse_number(&value, locale).map(|amount| format!("{:.2}", amount)).unwrap_or_else(|| {
+                warn!("Could not parse '{}' as a price", value);
+                String::new()
+            }))
+            .collect(),
+        Transform::ParseDate { format } => values
+            .into_iter()
+            .map(|value| parse_date(&value, format).unwrap_or_else(|| {
+                warn!("Could not parse '{}' as a date with format '{}'", value, format);
+                String::new()
+            }))
+            .collect(),
+        Transform::Split { separator } => values
+            .iter()
+            .flat_map(|value| value.split(separator.as_str()))
+            .map(|part| part.trim().to_string())
+            .filter(|part| !part.is_empty())
+            .collect(),
+        Transform::Join { separator } => vec![values.join(separator)],
+        Transform::DefaultIfEmpty { value: default } => {
+            if values.is_empty() {
+                vec![default.clone()]
+            } else {
+                values
+                    .into_iter()
+                    .map(|value| if value.trim().is_empty() { default.clone() } else { value })
+                    .collect()
+            }
+        }
+        Transform::Dedupe => {
+            let mut seen = HashSet::new();
+            values.into_iter().filter(|value| seen.insert(value.clone())).collect()
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| anyhow!("Invalid regex pattern '{}' in transform: {}", pattern, e))
+}
+
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        format!("{}", number as i64)
+    } else {
+        number.to_string()
+    }
+}
+
+/// Parses `value` with a chrono format string and returns it as RFC 3339,
+/// or as `YYYY-MM-DD` / `YYYY-MM-DDTHH:MM:SS` when it has no time zone or
+/// no time.
+fn parse_date(value: &str, format: &str) -> Option<String> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_str(value, format) {
+        return Some(date.to_rfc3339());
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+        return Some(date.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    NaiveDate::parse_from_str(value, format)
+        .ok()
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_text_transforms() {
+        let pipeline = vec![
+            Transform::Trim,
+            Transform::Lowercase,
+            Transform::RegexReplace { pattern: r"\s+".to_string(), replacement: "-".to_string() },
+            Transform::Substring { start: 0, length: Some(10) },
+            Transform::Dedupe,
+        ];
+        assert_eq!(
+            apply(&pipeline, strings(&["  Blue Widget ", "blue widget", "Red Gadget Deluxe"])).unwrap(),
+            strings(&["blue-widge", "red-gadget"])
+        );
+
+        let tags = vec![
+            Transform::Split { separator: ",".to_string() },
+            Transform::Dedupe,
+            Transform::Join { separator: " | ".to_string() },
+        ];
+        assert_eq!(apply(&tags, strings(&["new, sale", "sale,,featured"])).unwrap(), strings(&["new | sale | featured"]));
+
+        let fallback = vec![Transform::DefaultIfEmpty { value: "n/a".to_string() }];
+        assert_eq!(apply(&fallback, strings(&["x", " "])).unwrap(), strings(&["x", "n/a"]));
+        assert_eq!(apply(&fallback, Vec::new()).unwrap(), strings(&["n/a"]));
+
+        assert!(validate(&[Transform::RegexReplace { pattern: "(".to_string(), replacement: String::new() }]).is_err());
+    }
+
+    #[test]
+    fn test_parse_transforms() {
+        let number = |locale: &str| vec![Transform::ParseNumber { locale: locale.to_string() }];
+        assert_eq!(apply(&number("en-US"), strings(&["1,234.5 kg", "12"])).unwrap(), strings(&["1234.5", "12"]));
+        assert_eq!(apply(&number("de-DE"), strings(&["1.234,5"])).unwrap(), strings(&["1234.5"]));
+        assert_eq!(apply(&number("en"), strings(&["-3", "n/a"])).unwrap(), strings(&["-3", ""]));
+
+        let price = vec![Transform::ParseCurrency { locale: "fr-FR".to_string() }];
+        assert_eq!(apply(&price, strings(&["1 299,90 €", "(5,00 €)"])).unwrap(), strings(&["1299.90", "-5.00"]));
+
+        let date = |format: &str| vec![Transform::ParseDate { format: format.to_string() }];
+        assert_eq!(apply(&date("%d/%m/%Y"), strings(&["31/12/2024", "soon"])).unwrap(), strings(&["2024-12-31", ""]));
+        assert_eq!(
+            apply(&date("%B %d, %Y %H:%M"), strings(&["March 5, 2024 14:30"])).unwrap(),
+            strings(&["2024-03-05T14:30:00"])
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use regex::Regex;
+use crate::models::{NumericRange, ScrapedData, ValidationRules};
+use crate::numbers::parse_number;
+
+// Validation runs on the transformed data of a scrape that fetched fine. It
+// catches pages that load but no longer hold what the job expects, such as
+// captcha or error pages served with a 200 status.
+
+/// Describes every way `data` breaks `rules`, or nothing if it doesn't.
+/// Numbers are read as written for `locale`.
+pub fn check(rules: &ValidationRules, locale: &str, data: &ScrapedData) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    let count = data.len();
+    if let Some(min) = rules.min_items {
+        if count < min {
+            violations.push(format!("expected at least {} items, got {}", min, count));
+        }
+    }
+    if let Some(max) = rules.max_items {
+        if count > max {
+            violations.push(format!("expected at most {} items, got {}", max, count));
+        }
+    }
+
+    if let Some(pattern) = &rules.item_pattern {
+        check_pattern("item", pattern, data.values.iter(), &mut violations);
+    }
+    if let Some(range) = &rules.value_range {
+        check_range("item", range, locale, data.values.iter(), &mut violations);
+    }
+
+    for field in &rules.required_fields {
+        let missing = data.records
+            .iter()
+            .filter(|record| record.get(field).is_none_or(|value| value.trim().is_empty()))
+            .count();
+        if missing > 0 {
+            violations.push(format!("required field '{}' is empty in {} of {} records", field, missing, data.records.len()));
+        }
+    }
+    for (field, pattern) in &rules.field_patterns {
+        let values = data.records.iter().filter_map(|record| record.get(field));
+        check_pattern(&format!("field '{}'", field), pattern, values, &mut violations);
+    }
+    for (field, range) in &rules.field_ranges {
+        let values = data.records.iter().filter_map(|record| record.get(field));
+        check_range(&format!("field '{}'", field), range, locale, values, &mut violations);
+    }
+
+    violations
+}
+
+/// Checks that the rules' patterns compile, so mistakes show up when the
+/// job is saved rather than as failed runs.
+pub fn validate_rules(rules: &ValidationRules) -> anyhow::Result<()> {
+    for pattern in rules.item_pattern.iter().chain(rules.field_patterns.values()) {
+        Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid validation pattern '{}': {}", pattern, e))?;
+    }
+    Ok(())
+}
+
+fn check_pattern<'a>(subject: &str, pattern: &str, values: impl Iterator<Item = &'a String>, violations: &mut Vec<String>) {
+    let regex = match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            violations.push(format!("invalid pattern '{}' for {}: {}", pattern, subject, e));
+            return;
+        }
+    };
+    let failing: Vec<&String> = values.filter(|value| !regex.is_match(value)).collect();
+    if let Some(first) = failing.first() {
+        violations.push(format!(
+            "{} {} value(s) do not match '{}', e.g. '{}'",
+            failing.len(), subject, pattern, preview(first)
+        ));
+    }
+}
+
+fn check_range<'a>(subject: &str, range: &NumericRange, locale: &str, values: impl Iterator<Item = &'a String>, violations: &mut Vec<String>) {
+    let failing: Vec<&String> = values
+        .filter(|value| match parse_number(value, locale) {
+            Some(number) => range.min.is_some_and(|min| number < min) || range.max.is_some_and(|max| number > max),
+            None => true,
+        })
+        .collect();
+    if let Some(first) = failing.first() {
+        violations.push(format!(
+            "{} {} value(s) are not numbers within {}, e.g. '{}'",
+            failing.len(), subject, describe_range(range), preview(first)
+        ));
+    }
+}
+
+fn describe_range(range: &NumericRange) -> String {
+    match (range.min, range.max) {
+        (Some(min), Some(max)) => format!("{}..={}", min, max),
+        (Some(min), None) => format!(">= {}", min),
+        (None, Some(max)) => format!("<= {}", max),
+        (None, None) => "any range".to_string(),
+    }
+}
+
+fn preview(value: &str) -> String {
+    const MAX_CHARS: usize = 60;
+    if value.chars().count() > MAX_CHARS {
+        format!("{}...", value.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ScrapedRecord;
+    use crate::numbers::DEFAULT_LOCALE;
+
+    fn record(pairs: &[(&str, &str)]) -> ScrapedRecord {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_value_rules() {
+        let rules = ValidationRules {
+            min_items: Some(2),
+            item_pattern: Some(r"^\d+(\.\d+)?$".to_string()),
+            value_range: Some(NumericRange { min: Some(1.0), max: Some(100.0) }),
+            ..Default::default()
+        };
+        let good = ScrapedData { values: vec!["10".to_string(), "99.5".to_string()], ..Default::default() };
+        assert!(check(&rules, DEFAULT_LOCALE, &good).is_empty());
+
+        let captcha = ScrapedData { values: vec!["Please verify you are a human".to_string()], ..Default::default() };
+        let violations = check(&rules, DEFAULT_LOCALE, &captcha);
+        assert_eq!(violations.len(), 3);
+        assert_eq!(violations[0], "expected at least 2 items, got 1");
+
+        // Prices and grouped numbers are read the same way alerts read them
+        let formatted = ScrapedData { values: vec!["$19.99".to_string(), "1,299".to_string()], ..Default::default() };
+        let price_rules = ValidationRules {
+            value_range: Some(NumericRange { min: Some(1.0), max: Some(2000.0) }),
+            ..Default::default()
+        };
+        assert!(check(&price_rules, DEFAULT_LOCALE, &formatted).is_empty());
+
+        let out_of_range = ScrapedData { values: vec!["5".to_string(), "500".to_string()], ..Default::default() };
+        assert_eq!(check(&rules, DEFAULT_LOCALE, &out_of_range), vec!["1 item value(s) are not numbers within 1..=100, e.g. '500'"]);
+
+        // Read with the job's locale, "1.500" is fifteen hundred
+        let german = ScrapedData { values: vec!["12,50 €".to_string(), "1.500 €".to_string()], ..Default::default() };
+        assert!(check(&price_rules, "de-DE", &german).is_empty());
+        let under_100 = ValidationRules { value_range: rules.value_range.clone(), ..Default::default() };
+        assert_eq!(check(&under_100, "de-DE", &german), vec!["1 item value(s) are not numbers within 1..=100, e.g. '1.500 €'"]);
+    }
+
+    #[test]
+    fn test_record_rules() {
+        let mut rules = ValidationRules {
+            max_items: Some(5),
+            required_fields: vec!["title".to_string()],
+            ..Default::default()
+        };
+        rules.field_patterns.insert("sku".to_string(), "^[A-Z]\\d+$".to_string());
+        rules.field_ranges.insert("price".to_string(), NumericRange { min: Some(0.0), max: None });
+
+        let data = ScrapedData {
+            records: vec![
+                record(&[("title", "Widget"), ("sku", "A1"), ("price", "10")]),
+                record(&[("title", " "), ("sku", "b2"), ("price", "-1")]),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(check(&rules, DEFAULT_LOCALE, &data), vec![
+            "required field 'title' is empty in 1 of 2 records",
+            "1 field 'sku' value(s) do not match '^[A-Z]\\d+$', e.g. 'b2'",
+            "1 field 'price' value(s) are not numbers within >= 0, e.g. '-1'",
+        ]);
+
+        rules.field_patterns.insert("sku".to_string(), "(".to_string());
+        assert!(validate_rules(&rules).is_err());
+    }
+}
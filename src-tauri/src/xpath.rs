@@ -0,0 +1,344 @@
+use scraper::{ElementRef, Html, Node};
+use sxd_xpath::{nodeset, Value};
+use sxd_document::dom::{ChildOfElement, Element, ParentOfChild};
+use sxd_document::Package;
+use crate::models::{DataType, SelectorMatch};
+use crate::scraper::{own_text, visible_text, TextChild, TextElement};
+use anyhow::{Result, anyhow};
+
+// XPath is evaluated with sxd-xpath, which works on XML. Pages are parsed
+// with the same forgiving HTML parser used for CSS selectors and then
+// re-serialized as well-formed XML, so broken markup behaves the same way
+// for both selector types.
+
+pub fn validate_xpath(expression: &str) -> Result<()> {
+    XPathExpression::parse(expression).map(|_| ())
+}
+
+/// A parsed expression, evaluated against any number of documents.
+pub struct XPathExpression {
+    source: String,
+    xpath: sxd_xpath::XPath,
+}
+
+impl XPathExpression {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let xpath = sxd_xpath::Factory::new()
+            .build(expression)
+            .map_err(|e| anyhow!("Invalid XPath expression '{}': {:?}", expression, e))?
+            .ok_or_else(|| anyhow!("Empty XPath expression"))?;
+        Ok(XPathExpression { source: expression.to_string(), xpath })
+    }
+}
+
+/// Evaluates `expression` against an HTML document. Node sets produce one
+/// value per node, in document order; strings, numbers and booleans produce
+/// a single value.
+pub fn select_values(html: &str, expression: &str, data_type: &DataType) -> Result<Vec<String>> {
+    XmlDocument::from_html(html)?.select_values(&XPathExpression::parse(expression)?, data_type)
+}
+
+/// Like `select_values`, but keeps empty values and the location path of
+/// each node. Strings, numbers and booleans have an empty path.
+pub fn select_matches(html: &str, expression: &str, data_type: &DataType) -> Result<Vec<SelectorMatch>> {
+    XmlDocument::from_html(html)?.select_matches(&XPathExpression::parse(expression)?, data_type)
+}
+
+/// The XML copy of a page or of one element, built once so that several
+/// expressions can be evaluated against it.
+pub struct XmlDocument {
+    package: Package,
+}
+
+impl XmlDocument {
+    pub fn from_html(html: &str) -> Result<Self> {
+        Self::from_xml(&html_to_xml(&Html::parse_document(html)))
+    }
+
+    /// An element of an already parsed page, e.g. one item of a record
+    /// job. The element is the document root, so `.//x` and `//x` both
+    /// stay within it.
+    pub fn from_element(element: ElementRef) -> Result<Self> {
+        let mut xml = String::new();
+        write_node(*element, &mut xml);
+        Self::from_xml(&xml)
+    }
+
+    fn from_xml(xml: &str) -> Result<Self> {
+        let package = sxd_document::parser::parse(xml)
+            .map_err(|e| anyhow!("Failed to prepare document for XPath: {:?}", e))?;
+        Ok(XmlDocument { package })
+    }
+
+    pub fn select_values(&self, expression: &XPathExpression, data_type: &DataType) -> Result<Vec<String>> {
+        Ok(self.select_matches(expression, data_type)?
+            .into_iter()
+            .map(|selected| selected.value)
+            .filter(|value| !value.is_empty())
+            .collect())
+    }
+
+    pub fn select_matches(&self, expression: &XPathExpression, data_type: &DataType) -> Result<Vec<SelectorMatch>> {
+        let document = self.package.as_document();
+        let value = expression.xpath.evaluate(&sxd_xpath::Context::new(), document.root())
+            .map_err(|e| anyhow!("Failed to evaluate XPath expression '{}': {:?}", expression.source, e))?;
+
+        let scalar = |value: String| vec![SelectorMatch { path: String::new(), value }];
+        Ok(match value {
+            Value::Nodeset(nodes) => nodes
+                .document_order()
+                .into_iter()
+                .map(|node| SelectorMatch { path: node_path(node), value: node_value(node, data_type) })
+                .collect(),
+            Value::String(value) => scalar(value.trim().to_string()),
+            Value::Number(number) if number.fract() == 0.0 && number.is_finite() => scalar(format!("{}", number as i64)),
+            Value::Number(number) => scalar(number.to_string()),
+            Value::Boolean(value) => scalar(value.to_string()),
+        })
+    }
+}
+
+impl<'d> TextElement<'d> for Element<'d> {
+    fn tag_name(&self) -> &'d str {
+        self.name().local_part()
+    }
+
+    fn text_children(&self) -> Vec<TextChild<'d, Self>> {
+        self.children()
+            .into_iter()
+            .filter_map(|child| match child {
+                ChildOfElement::Text(text) => Some(TextChild::Text(text.text())),
+                ChildOfElement::Element(element) => Some(TextChild::Element(element)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn node_path(node: nodeset::Node) -> String {
+    match node {
+        nodeset::Node::Element(element) => element_path(element),
+        nodeset::Node::Attribute(attribute) => match attribute.parent() {
+            Some(element) => format!("{}/@{}", element_path(element), attribute.name().local_part()),
+            None => format!("@{}", attribute.name().local_part()),
+        },
+        nodeset::Node::Text(text) => match text.parent() {
+            Some(element) => format!("{}/text()", element_path(element)),
+            None => "text()".to_string(),
+        },
+        nodeset::Node::Root(_) => "/".to_string(),
+        _ => String::new(),
+    }
+}
+
+// Positions are only given where siblings share the element's name
+fn element_path(element: Element) -> String {
+    let name = element.name().local_part();
+    match element.parent() {
+        Some(ParentOfChild::Element(parent)) => {
+            let same_name: Vec<Element> = parent.children()
+                .into_iter()
+                .filter_map(|child| match child {
+                    ChildOfElement::Element(sibling) if sibling.name().local_part() == name => Some(sibling),
+                    _ => None,
+                })
+                .collect();
+            let step = if same_name.len() > 1 {
+                let position = same_name.iter().position(|sibling| *sibling == element).unwrap_or(0) + 1;
+                format!("{}[{}]", name, position)
+            } else {
+                name.to_string()
+            };
+            format!("{}/{}", element_path(parent), step)
+        }
+        _ => format!("/{}", name),
+    }
+}
+
+fn node_value(node: nodeset::Node, data_type: &DataType) -> String {
+    match (node, data_type) {
+        (nodeset::Node::Element(element), DataType::Attribute(name)) => {
+            element.attribute_value(name.as_str()).unwrap_or_default().to_string()
+        }
+        (nodeset::Node::Element(element), DataType::InnerHtml) => {
+            let mut html = String::new();
+            for child in element.children() {
+                write_xml_child(child, &mut html);
+            }
+            html.trim().to_string()
+        }
+        (nodeset::Node::Element(element), DataType::OuterHtml) => {
+            let mut html = String::new();
+            write_xml_child(ChildOfElement::Element(element), &mut html);
+            html
+        }
+        (nodeset::Node::Element(element), DataType::OwnText) => own_text(&element),
+        (nodeset::Node::Element(element), DataType::CleanText) => visible_text(&element),
+        (nodeset::Node::Attribute(attribute), _) => attribute.value().to_string(),
+        (node, _) => node.string_value().trim().to_string(),
+    }
+}
+
+// Matched nodes are serialized back from the XML copy of the page, so
+// markup is normalized (e.g. `<br>` becomes `<br></br>`)
+fn write_xml_child(child: ChildOfElement, html: &mut String) {
+    match child {
+        ChildOfElement::Element(element) => {
+            let name = element.name().local_part();
+            html.push('<');
+            html.push_str(name);
+            for attribute in element.attributes() {
+                html.push(' ');
+                html.push_str(attribute.name().local_part());
+                html.push_str("=\"");
+                push_escaped(html, attribute.value(), true);
+                html.push('"');
+            }
+            html.push('>');
+            for grandchild in element.children() {
+                write_xml_child(grandchild, html);
+            }
+            html.push_str("</");
+            html.push_str(name);
+            html.push('>');
+        }
+        ChildOfElement::Text(text) => push_escaped(html, text.text(), false),
+        _ => {}
+    }
+}
+
+fn html_to_xml(document: &Html) -> String {
+    let mut xml = String::new();
+    for child in document.tree.root().children() {
+        write_node(child, &mut xml);
+    }
+    xml
+}
+
+fn write_node(node: ego_tree::NodeRef<Node>, xml: &mut String) {
+    match node.value() {
+        Node::Element(element) if is_xml_name(element.name()) => {
+            xml.push('<');
+            xml.push_str(element.name());
+            for (name, value) in element.attrs() {
+                if is_xml_name(name) {
+                    xml.push(' ');
+                    xml.push_str(name);
+                    xml.push_str("=\"");
+                    push_escaped(xml, value, true);
+                    xml.push('"');
+                }
+            }
+            xml.push('>');
+            for child in node.children() {
+                write_node(child, xml);
+            }
+            xml.push_str("</");
+            xml.push_str(element.name());
+            xml.push('>');
+        }
+        Node::Text(text) => push_escaped(xml, text, false),
+        // Elements with names XML can't represent keep their content
+        Node::Element(_) | Node::Fragment | Node::Document => {
+            for child in node.children() {
+                write_node(child, xml);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Prefixed names are skipped too, since they would need namespace declarations
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn push_escaped(xml: &mut String, text: &str, in_attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' if in_attribute => xml.push_str("&quot;"),
+            // Control characters are not allowed in XML documents
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => xml.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"
+        <html><body>
+            <div class="product" data-sku="A1">
+                <h2>Widget</h2><span>In stock</span><br>
+                <p>Price: <b>$10</b> &amp; free shipping</p>
+            </div>
+            <div class="product" data-sku="B2" :class="x">
+                <h2>Gadget</h2><span>Sold out</span>
+            </div>
+        </body></html>
+    "#;
+
+    #[test]
+    fn test_select_values() {
+        assert_eq!(
+            select_values(HTML, "//div[@class='product']/h2", &DataType::Text).unwrap(),
+            vec!["Widget", "Gadget"]
+        );
+        // Text-content predicates and ancestor axes
+        assert_eq!(
+            select_values(HTML, "//span[contains(text(), 'Sold')]/ancestor::div[1]", &DataType::Attribute("data-sku".to_string())).unwrap(),
+            vec!["B2"]
+        );
+        assert_eq!(
+            select_values(HTML, "//div/@data-sku", &DataType::Text).unwrap(),
+            vec!["A1", "B2"]
+        );
+        assert_eq!(
+            select_values(HTML, "normalize-space(//p)", &DataType::Text).unwrap(),
+            vec!["Price: $10 & free shipping"]
+        );
+        assert_eq!(select_values(HTML, "count(//h2)", &DataType::Text).unwrap(), vec!["2"]);
+        assert_eq!(select_values(HTML, "//p", &DataType::OwnText).unwrap(), vec!["Price: & free shipping"]);
+        assert_eq!(
+            select_values(HTML, "//p", &DataType::InnerHtml).unwrap(),
+            vec!["Price: <b>$10</b> &amp; free shipping"]
+        );
+    }
+
+    #[test]
+    fn test_select_matches() {
+        let matches = select_matches(HTML, "//div/h2 | //div[2]/@data-sku", &DataType::Text).unwrap();
+        let paths: Vec<&str> = matches.iter().map(|selected| selected.path.as_str()).collect();
+        assert_eq!(paths, vec!["/html/body/div[1]/h2", "/html/body/div[2]/@data-sku", "/html/body/div[2]/h2"]);
+        assert_eq!(matches[1].value, "B2");
+    }
+
+    #[test]
+    fn test_element_document() {
+        let page = Html::parse_document(HTML);
+        let selector = scraper::Selector::parse("div.product").unwrap();
+        let second = page.select(&selector).nth(1).unwrap();
+
+        let item = XmlDocument::from_element(second).unwrap();
+        let select = |expression: &str, data_type: &DataType| {
+            item.select_values(&XPathExpression::parse(expression).unwrap(), data_type).unwrap()
+        };
+        assert_eq!(select("//h2", &DataType::Text), vec!["Gadget"]);
+        assert_eq!(select(".//span", &DataType::CleanText), vec!["Sold out"]);
+        assert_eq!(select("/div/@data-sku", &DataType::Text), vec!["B2"]);
+    }
+
+    #[test]
+    fn test_validate_xpath() {
+        assert!(validate_xpath("//a[@href]").is_ok());
+        assert!(validate_xpath("//a[").is_err());
+        assert!(validate_xpath("").is_err());
+    }
+}
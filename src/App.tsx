@@ -41,6 +41,8 @@ interface JobStats {
   active_jobs: number;
   total_results: number;
   last_run?: string;
+  degraded_jobs: number;
+  degraded_job_ids: number[];
 }
 
 interface EmailConfig {
@@ -53,6 +55,13 @@ interface EmailConfig {
   use_tls: boolean;
 }
 
+interface ScraperSettings {
+  max_concurrent_requests: number;
+  max_concurrent_per_host: number;
+  min_host_delay_ms: number;
+  respect_robots_txt: boolean;
+}
+
 interface ExportRequest {
   job_id: number;
   format: 'CSV' | 'JSON' | 'HTML';
@@ -82,6 +91,7 @@ function App() {
   const [results, setResults] = useState<ScrapingResult[]>([]);
   const [stats, setStats] = useState<JobStats | null>(null);
   const [emailConfig, setEmailConfig] = useState<EmailConfig | null>(null);
+  const [scraperSettings, setScraperSettings] = useState<ScraperSettings | null>(null);
   const [exportFiles, setExportFiles] = useState<ExportFileInfo[]>([]);
   const [selectedFile, setSelectedFile] = useState<ExportFileInfo | null>(null);
   const [fileContent, setFileContent] = useState<string>('');
@@ -125,6 +135,7 @@ function App() {
       await loadJobs();
       await loadStats();
       await loadEmailConfig();
+      await loadScraperSettings();
       showMessage('Application initialized successfully', 'success');
     } catch (error) {
       showMessage(`Failed to initialize app: ${error}`, 'error');
@@ -163,6 +174,15 @@ function App() {
     }
   };
 
+  const loadScraperSettings = async () => {
+    try {
+      const settings = await invoke<ScraperSettings>('get_scraper_settings');
+      setScraperSettings(settings);
+    } catch (error) {
+      console.error('Failed to load scraper settings:', error);
+    }
+  };
+
   const loadJobResults = async (jobId: number) => {
     try {
       const jobResults = await invoke<ScrapingResult[]>('get_job_results', { jobId, limit: 100 });
@@ -354,8 +374,13 @@ function App() {
       setLoadingProgress(100);
       setLoadingMessage('Completing test...');
       
-      const result = await invoke<string[]>('test_scrape_job', { job });
-      showMessage(`Test successful! Found ${result.length} results: ${result.slice(0, 3).join(', ')}${result.length > 3 ? '...' : ''}`, 'success');
+      const { before, after } = await invoke<{ before: string[]; after: string[] }>('test_scrape_job', { job });
+      const preview = (values: string[]) => `${values.slice(0, 3).join(', ')}${values.length > 3 ? '...' : ''}`;
+      const changed = before.join('\n') !== after.join('\n');
+      showMessage(
+        `Test successful! Found ${after.length} results: ${preview(after)}${changed ? ` (before transforms: ${preview(before)})` : ''}`,
+        'success'
+      );
     } catch (error) {
       showMessage(`Test failed: ${error}`, 'error');
     } finally {
@@ -407,6 +432,19 @@ function App() {
     }
   };
 
+  const handleSaveScraperSettings = async (settings: ScraperSettings) => {
+    try {
+      setIsLoading(true);
+      await invoke('save_scraper_settings', { settings });
+      setScraperSettings(settings);
+      showMessage('Scraping limits saved successfully', 'success');
+    } catch (error) {
+      showMessage(`Failed to save scraping limits: ${error}`, 'error');
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
   const handleTestEmailConnection = async () => {
     try {
       setIsLoading(true);
@@ -627,6 +665,8 @@ function App() {
             emailConfig={emailConfig}
             onSaveEmailConfig={handleSaveEmailConfig}
             onTestEmailConnection={handleTestEmailConnection}
+            scraperSettings={scraperSettings}
+            onSaveScraperSettings={handleSaveScraperSettings}
             theme={theme}
             onThemeToggle={handleThemeToggle}
             isLoading={isLoading}
@@ -1242,12 +1282,14 @@ interface SettingsTabProps {
   emailConfig: EmailConfig | null;
   onSaveEmailConfig: (config: EmailConfig) => void;
   onTestEmailConnection: () => void;
+  scraperSettings: ScraperSettings | null;
+  onSaveScraperSettings: (settings: ScraperSettings) => void;
   theme: 'dark' | 'light';
   onThemeToggle: (theme: 'dark' | 'light') => void;
   isLoading: boolean;
 }
 
-function SettingsTab({ emailConfig, onSaveEmailConfig, onTestEmailConnection, theme, onThemeToggle, isLoading }: SettingsTabProps) {
+function SettingsTab({ emailConfig, onSaveEmailConfig, onTestEmailConnection, scraperSettings, onSaveScraperSettings, theme, onThemeToggle, isLoading }: SettingsTabProps) {
   const [formData, setFormData] = useState<EmailConfig>(
     emailConfig || {
       smtp_server: '',
@@ -1259,6 +1301,14 @@ function SettingsTab({ emailConfig, onSaveEmailConfig, onTestEmailConnection, th
       use_tls: true,
     }
   );
+  const [limits, setLimits] = useState<ScraperSettings>(
+    scraperSettings || {
+      max_concurrent_requests: 4,
+      max_concurrent_per_host: 1,
+      min_host_delay_ms: 1000,
+      respect_robots_txt: true,
+    }
+  );
   const [activeSection, setActiveSection] = useState<string>('appearance');
 
   useEffect(() => {
@@ -1267,11 +1317,22 @@ function SettingsTab({ emailConfig, onSaveEmailConfig, onTestEmailConnection, th
     }
   }, [emailConfig]);
 
+  useEffect(() => {
+    if (scraperSettings) {
+      setLimits(scraperSettings);
+    }
+  }, [scraperSettings]);
+
   const handleSubmit = (e: React.FormEvent) => {
     e.preventDefault();
     onSaveEmailConfig(formData);
   };
 
+  const handleLimitsSubmit = (e: React.FormEvent) => {
+    e.preventDefault();
+    onSaveScraperSettings(limits);
+  };
+
   const openLinkedIn = () => {
     window.open('https://www.linkedin.com/in/md-jobayer-arafat-a14b61284/', '_blank');
   };
@@ -1302,6 +1363,13 @@ function SettingsTab({ emailConfig, onSaveEmailConfig, onTestEmailConnection, th
           <span className="nav-icon">📧</span>
           <span>Email Configuration</span>
         </button>
+        <button 
+          className={`nav-item ${activeSection === 'scraping' ? 'active' : ''}`}
+          onClick={() => setActiveSection('scraping')}
+        >
+          <span className="nav-icon">🚦</span>
+          <span>Scraping Limits</span>
+        </button>
         <button 
           className={`nav-item ${activeSection === 'about' ? 'active' : ''}`}
           onClick={() => setActiveSection('about')}
@@ -1471,6 +1539,74 @@ function SettingsTab({ emailConfig, onSaveEmailConfig, onTestEmailConnection, th
           </div>
         )}
 
+        {activeSection === 'scraping' && (
+          <div className="settings-panel fade-in">
+            <div className="panel-header">
+              <h2>Scraping Limits</h2>
+              <p>Limit how hard all jobs together may hit websites</p>
+            </div>
+            
+            <form className="modern-form" onSubmit={handleLimitsSubmit}>
+              <div className="form-grid">
+                <div className="input-group">
+                  <label className="input-label">Max Concurrent Requests</label>
+                  <input
+                    type="number"
+                    className="modern-input"
+                    min={1}
+                    value={limits.max_concurrent_requests}
+                    onChange={(e) => setLimits({ ...limits, max_concurrent_requests: parseInt(e.target.value) || 1 })}
+                    required
+                  />
+                </div>
+                
+                <div className="input-group">
+                  <label className="input-label">Max Concurrent Requests per Site</label>
+                  <input
+                    type="number"
+                    className="modern-input"
+                    min={1}
+                    value={limits.max_concurrent_per_host}
+                    onChange={(e) => setLimits({ ...limits, max_concurrent_per_host: parseInt(e.target.value) || 1 })}
+                    required
+                  />
+                </div>
+              </div>
+
+              <div className="input-group">
+                <label className="input-label">Delay Between Requests to a Site (ms)</label>
+                <input
+                  type="number"
+                  className="modern-input"
+                  min={0}
+                  value={limits.min_host_delay_ms}
+                  onChange={(e) => setLimits({ ...limits, min_host_delay_ms: parseInt(e.target.value) || 0 })}
+                  required
+                />
+              </div>
+
+              <div className="checkbox-group">
+                <label className="modern-checkbox">
+                  <input
+                    type="checkbox"
+                    checked={limits.respect_robots_txt}
+                    onChange={(e) => setLimits({ ...limits, respect_robots_txt: e.target.checked })}
+                  />
+                  <span className="checkmark"></span>
+                  <span className="checkbox-text">Respect robots.txt (a longer Crawl-delay overrides the delay above)</span>
+                </label>
+              </div>
+
+              <div className="form-actions-modern">
+                <button type="submit" className="btn-primary" disabled={isLoading}>
+                  <span className="btn-icon">💾</span>
+                  Save Limits
+                </button>
+              </div>
+            </form>
+          </div>
+        )}
+
         {activeSection === 'about' && (
           <div className="settings-panel fade-in">
             <div className="panel-header">